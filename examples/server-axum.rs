@@ -0,0 +1,31 @@
+use std::env;
+
+use axum::{extract::ConnectInfo, routing::get, Router};
+use mptcp::tokio::{MptcpConnectionInfo, MptcpListenerExt};
+use tokio::net::TcpListener;
+
+async fn amiusingmptcp(ConnectInfo(info): ConnectInfo<MptcpConnectionInfo>) -> String {
+    if info.is_mptcp() {
+        format!("{}: you are using MPTCP", info.remote_addr)
+    } else {
+        format!("{}: you are not using MPTCP", info.remote_addr)
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let addr = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:8080".to_string());
+
+    let app = Router::new().route("/", get(amiusingmptcp));
+
+    let listener = TcpListener::bind_mptcp(addr).await.unwrap();
+
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<MptcpConnectionInfo>(),
+    )
+    .await
+    .unwrap();
+}
@@ -1,40 +1,20 @@
 use std::env;
 
-use axum::{
-    extract::{connect_info::Connected, ConnectInfo},
-    http::Request,
-    routing::get,
-    Router,
-};
-use hyper::body::Incoming;
+use axum::{routing::get, Extension, Router};
 use hyper_util::{
     rt::{TokioExecutor, TokioIo},
     server,
+    service::TowerToHyperService,
 };
-use mptcp::{tokio::MptcpListenerExt, MptcpExt, MptcpStatus};
-use tokio::net::{TcpListener, TcpStream};
-use tower_service::Service;
-
-#[derive(Debug, Clone)]
-struct MptcpInfo {
-    use_mptcp: bool,
-}
-
-impl Connected<&TcpStream> for MptcpInfo {
-    fn connect_info(sock: &TcpStream) -> Self {
-        let use_mptcp = matches!(
-            sock.mptcp_status(),
-            MptcpStatus::Mptcp {
-                has_fallback: false,
-            }
-        );
-
-        Self { use_mptcp }
-    }
-}
+use mptcp::{
+    tokio::{MptcpConnectionInfo, MptcpListenerExt, MptcpStream},
+    tower::MptcpLayer,
+};
+use tokio::net::TcpListener;
+use tower_layer::Layer;
 
-async fn amiusingmptcp(ConnectInfo(info): ConnectInfo<MptcpInfo>) -> &'static str {
-    if info.use_mptcp {
+async fn amiusingmptcp(Extension(info): Extension<MptcpConnectionInfo>) -> &'static str {
+    if info.is_mptcp() {
         "You are using MPTCP"
     } else {
         "You are not using MPTCP"
@@ -51,25 +31,19 @@ async fn main() {
 
     let listener = TcpListener::bind_mptcp_force(addr).await.unwrap();
 
-    let handle = tokio::spawn(async move {
-        let mut make_service = app.into_make_service_with_connect_info::<MptcpInfo>();
-        loop {
-            let (socket, _remote_addr) = listener.accept().await.unwrap();
-            let tower_service = make_service.call(&socket).await.unwrap();
-            tokio::spawn(async move {
-                let socket = TokioIo::new(socket);
-                let hyper_service =
-                    hyper::service::service_fn(move |request: Request<Incoming>| {
-                        tower_service.clone().call(request)
-                    });
-                if let Err(err) = server::conn::auto::Builder::new(TokioExecutor::new())
-                    .serve_connection(socket, hyper_service)
-                    .await
-                {
-                    println!("Failed to serve connection: {}", err);
-                }
-            });
-        }
-    });
-    let _ = handle.await;
+    loop {
+        let (socket, remote_addr) = listener.accept_mptcp().await.unwrap();
+        let socket = MptcpStream::from(socket);
+        let layer =
+            MptcpLayer::new(MptcpConnectionInfo::new(&socket, remote_addr)).response_header(true);
+        let service = TowerToHyperService::new(layer.layer(app.clone()));
+        tokio::spawn(async move {
+            if let Err(err) = server::conn::auto::Builder::new(TokioExecutor::new())
+                .serve_connection(TokioIo::new(socket), service)
+                .await
+            {
+                println!("Failed to serve connection: {}", err);
+            }
+        });
+    }
 }
@@ -1,11 +1,34 @@
-use std::{io, net::SocketAddr};
+#[cfg(not(target_os = "linux"))]
+use std::convert::Infallible;
+use std::{
+    future::Future,
+    io::{self, IoSlice, IoSliceMut},
+    net::SocketAddr,
+    os::fd::{AsRawFd, RawFd},
+    pin::Pin,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
 
-use async_std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use async_io::Async;
+use async_std::{
+    future,
+    io::{Read, Write},
+    net::{Incoming, TcpListener, TcpStream, ToSocketAddrs},
+    stream::Stream,
+    task::sleep,
+};
+use socket2::Socket;
 
-use crate::{sys::MptcpSocketBuilder, MptcpExt, MptcpOpt, MptcpSocket};
+use crate::{
+    event::{self, PmUpdate, POLL_INTERVAL},
+    race::race,
+    telemetry::{self, Attempt, Fallback},
+    AcceptFilter, ConnectOptions, FallbackCache, ListenOptions, MptcpExt, MptcpInfo, MptcpOpt,
+    MptcpSocket, MptcpSocketBuilder, MptcpStatus, MptcpSubflow,
+};
 
 /// Extension trait for async_std::net::TcpStream to support MPTCP.
-#[async_trait::async_trait(?Send)]
 pub trait MptcpStreamExt {
     type Output;
 
@@ -20,10 +43,13 @@ pub trait MptcpStreamExt {
     ///
     /// Returns an `io::Result` containing the MptcpSocket if the connection is successful,
     /// or an `io::Error` if an error occurs during the connection.
-    async fn connect_mptcp_opt<A: ToSocketAddrs>(
+    fn connect_mptcp_opt<A>(
         addr: A,
         opt: MptcpOpt,
-    ) -> io::Result<MptcpSocket<Self::Output>>;
+    ) -> impl Future<Output = io::Result<MptcpSocket<Self::Output>>> + Send
+    where
+        A: ToSocketAddrs + Send + Sync,
+        A::Iter: Send;
 
     /// Establishes an MPTCP connection with the given address. If MPTCP cannot be used
     /// the connection will fallback to a regular TCP connection.
@@ -36,8 +62,14 @@ pub trait MptcpStreamExt {
     ///
     /// Returns an `io::Result` containing the MptcpSocket if the connection is successful,
     /// or an `io::Error` if an error occurs during the connection.
-    async fn connect_mptcp<A: ToSocketAddrs>(addr: A) -> io::Result<MptcpSocket<Self::Output>> {
-        Self::connect_mptcp_opt(addr, MptcpOpt::Fallback).await
+    fn connect_mptcp<A>(
+        addr: A,
+    ) -> impl Future<Output = io::Result<MptcpSocket<Self::Output>>> + Send
+    where
+        A: ToSocketAddrs + Send + Sync,
+        A::Iter: Send,
+    {
+        Self::connect_mptcp_opt(addr, MptcpOpt::Fallback)
     }
 
     /// Establishes an MPTCP connection with the given address. Returns an error even if
@@ -51,15 +83,140 @@ pub trait MptcpStreamExt {
     ///
     /// Returns an `io::Result` containing the MptcpSocket if the connection is successful,
     /// or an `io::Error` if an error occurs during the connection.
-    async fn connect_mptcp_force<A: ToSocketAddrs>(addr: A) -> io::Result<Self::Output> {
-        Ok(Self::connect_mptcp_opt(addr, MptcpOpt::NoFallback)
-            .await?
-            .into_socket())
+    fn connect_mptcp_force<A>(addr: A) -> impl Future<Output = io::Result<Self::Output>> + Send
+    where
+        A: ToSocketAddrs + Send + Sync,
+        A::Iter: Send,
+    {
+        async move {
+            Ok(Self::connect_mptcp_opt(addr, MptcpOpt::NoFallback)
+                .await?
+                .into_socket())
+        }
     }
+
+    /// Races an MPTCP connection against a regular TCP connection to the given address.
+    ///
+    /// The MPTCP attempt is started first. The TCP attempt is started after `delay`,
+    /// or as soon as the MPTCP attempt fails. The first connection to be established
+    /// is returned and the other attempt is cancelled. This protects against paths
+    /// that silently drop SYNs carrying MPTCP options.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address to connect to.
+    /// * `delay` - The delay before starting the TCP attempt.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing `MptcpSocket::Mptcp` if the MPTCP attempt won,
+    /// `MptcpSocket::Tcp` if the TCP attempt won, or an `io::Error` if both failed.
+    fn connect_mptcp_race<A>(
+        addr: A,
+        delay: Duration,
+    ) -> impl Future<Output = io::Result<MptcpSocket<Self::Output>>> + Send
+    where
+        A: ToSocketAddrs + Send + Sync,
+        A::Iter: Send;
+
+    /// Establishes a connection with the given address, using `cache` to skip MPTCP
+    /// for destinations known to fall back to TCP.
+    ///
+    /// Destinations found in the cache are connected to with plain TCP directly. Otherwise
+    /// an MPTCP connection is attempted, falling back to TCP if MPTCP cannot be used, and
+    /// the destination is added to the cache if the established connection reports
+    /// a fallback to TCP.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address to connect to.
+    /// * `cache` - The cache of destinations falling back to TCP.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing the MptcpSocket if the connection is successful,
+    /// or an `io::Error` if an error occurs during the connection.
+    fn connect_mptcp_cached<A>(
+        addr: A,
+        cache: &FallbackCache,
+    ) -> impl Future<Output = io::Result<MptcpSocket<Self::Output>>> + Send
+    where
+        A: ToSocketAddrs + Send + Sync,
+        A::Iter: Send;
+
+    /// Establishes an MPTCP connection with the given address and MptcpOpt, starting the
+    /// initial subflow from the `local` address. The TCP connection used in case of
+    /// fallback is bound to the same address.
+    ///
+    /// # Arguments
+    ///
+    /// * `local` - The local address to bind to before connecting.
+    /// * `addr` - The address to connect to.
+    /// * `opt` - The MptcpOpt options for the connection.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing the MptcpSocket if the connection is successful,
+    /// or an `io::Error` if an error occurs during the connection.
+    fn connect_mptcp_from<A>(
+        local: SocketAddr,
+        addr: A,
+        opt: MptcpOpt,
+    ) -> impl Future<Output = io::Result<MptcpSocket<Self::Output>>> + Send
+    where
+        A: ToSocketAddrs + Send + Sync,
+        A::Iter: Send;
+
+    /// Establishes an MPTCP connection with the given address and MptcpOpt, starting the
+    /// initial subflow on the given network interface using `SO_BINDTODEVICE`. The TCP
+    /// connection used in case of fallback is bound to the same interface.
+    ///
+    /// Only supported on Linux.
+    ///
+    /// # Arguments
+    ///
+    /// * `interface` - The name of the network interface to use.
+    /// * `addr` - The address to connect to.
+    /// * `opt` - The MptcpOpt options for the connection.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing the MptcpSocket if the connection is successful,
+    /// or an `io::Error` if an error occurs during the connection.
+    fn connect_mptcp_from_device<A>(
+        interface: &str,
+        addr: A,
+        opt: MptcpOpt,
+    ) -> impl Future<Output = io::Result<MptcpSocket<Self::Output>>> + Send
+    where
+        A: ToSocketAddrs + Send + Sync,
+        A::Iter: Send;
+
+    /// Establishes an MPTCP connection with the given address and ConnectOptions. If
+    /// MPTCP cannot be used the connection will fallback to a regular TCP connection,
+    /// using the same options.
+    ///
+    /// Use `MptcpSocketBuilder` to configure a connection that does not fall back to TCP.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address to connect to.
+    /// * `options` - The options of the connection.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing the MptcpSocket if the connection is successful,
+    /// or an `io::Error` if an error occurs during the connection.
+    fn connect_mptcp_with<A>(
+        addr: A,
+        options: ConnectOptions,
+    ) -> impl Future<Output = io::Result<MptcpSocket<Self::Output>>> + Send
+    where
+        A: ToSocketAddrs + Send + Sync,
+        A::Iter: Send;
 }
 
 /// Extension trait for async_std::net::TcpListener.
-#[async_trait::async_trait(?Send)]
 pub trait MptcpListenerExt {
     type Output;
 
@@ -73,10 +230,13 @@ pub trait MptcpListenerExt {
     /// # Returns
     ///
     /// Returns an `io::Result` containing the MptcpSocket with the specified MptcpOpt.
-    async fn bind_mptcp_opt<A: ToSocketAddrs>(
+    fn bind_mptcp_opt<A>(
         addr: A,
         opt: MptcpOpt,
-    ) -> io::Result<MptcpSocket<Self::Output>>;
+    ) -> impl Future<Output = io::Result<MptcpSocket<Self::Output>>> + Send
+    where
+        A: ToSocketAddrs + Send + Sync,
+        A::Iter: Send;
 
     /// Binds an MPTCP socket to the specified address. If MPTCP cannot be used
     /// the connection will fallback to a regular TCP connection.
@@ -88,8 +248,12 @@ pub trait MptcpListenerExt {
     /// # Returns
     ///
     /// Returns an `io::Result` containing the MptcpSocket with the default MptcpOpt (Fallback).
-    async fn bind_mptcp<A: ToSocketAddrs>(addr: A) -> io::Result<MptcpSocket<Self::Output>> {
-        Self::bind_mptcp_opt(addr, MptcpOpt::Fallback).await
+    fn bind_mptcp<A>(addr: A) -> impl Future<Output = io::Result<MptcpSocket<Self::Output>>> + Send
+    where
+        A: ToSocketAddrs + Send + Sync,
+        A::Iter: Send,
+    {
+        Self::bind_mptcp_opt(addr, MptcpOpt::Fallback)
     }
 
     /// Binds an MPTCP socket to the specified address. Returns an error even if
@@ -102,21 +266,142 @@ pub trait MptcpListenerExt {
     /// # Returns
     ///
     /// Returns an `io::Result` containing the MptcpSocket with the MptcpOpt set to NoFallback.
-    async fn bind_mptcp_force<A: ToSocketAddrs>(addr: A) -> io::Result<Self::Output> {
-        Ok(Self::bind_mptcp_opt(addr, MptcpOpt::NoFallback)
-            .await?
-            .into_socket())
+    fn bind_mptcp_force<A>(addr: A) -> impl Future<Output = io::Result<Self::Output>> + Send
+    where
+        A: ToSocketAddrs + Send + Sync,
+        A::Iter: Send,
+    {
+        async move {
+            Ok(Self::bind_mptcp_opt(addr, MptcpOpt::NoFallback)
+                .await?
+                .into_socket())
+        }
     }
+
+    /// Binds an MPTCP socket to the specified address with the given ListenOptions. If
+    /// MPTCP cannot be used the listener will fallback to a regular TCP listener, using
+    /// the same options.
+    ///
+    /// Use `MptcpSocketBuilder` to configure a listener that does not fall back to TCP.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address to bind the socket to.
+    /// * `options` - The options of the listener.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing the MptcpSocket with the default MptcpOpt (Fallback).
+    fn bind_mptcp_with<A>(
+        addr: A,
+        options: ListenOptions,
+    ) -> impl Future<Output = io::Result<MptcpSocket<Self::Output>>> + Send
+    where
+        A: ToSocketAddrs + Send + Sync,
+        A::Iter: Send;
+
+    /// Accepts a new incoming connection from this listener.
+    ///
+    /// The connection is returned as `MptcpSocket::Mptcp` only if the peer negotiated
+    /// MPTCP, and as `MptcpSocket::Tcp` otherwise, including when the listener itself
+    /// is a TCP listener. Fallbacks can only be detected on Linux >= 5.16.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing the connection and the address of the peer.
+    fn accept_mptcp(
+        &self,
+    ) -> impl Future<Output = io::Result<(MptcpSocket<TcpStream>, SocketAddr)>> + Send;
+
+    /// Accepts a new incoming connection using MPTCP from this listener.
+    ///
+    /// Connections that do not use MPTCP are rejected by `filter`, and this method keeps
+    /// waiting for the next connection.
+    /// Returns `io::ErrorKind::Unsupported` on Linux < 5.16, where fallbacks cannot
+    /// be detected.
+    ///
+    /// # Arguments
+    ///
+    /// * `filter` - The AcceptFilter handling rejected connections.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing the connection and the address of the peer.
+    fn accept_mptcp_filtered(
+        &self,
+        filter: &AcceptFilter<TcpStream>,
+    ) -> impl Future<Output = io::Result<(TcpStream, SocketAddr)>> + Send;
+
+    /// Returns a stream over the connections received on this listener, as returned
+    /// by `accept_mptcp`. The stream never returns `None`.
+    fn incoming_mptcp(&self) -> IncomingMptcp<'_>;
 }
 
-async fn resolve_each_addr<A: ToSocketAddrs, F, T>(addr: &A, mut f: F) -> io::Result<T>
+/// Extension trait for MptcpSocketBuilder to create async_std::net sockets.
+pub trait MptcpSocketBuilderExt {
+    /// Establishes an MPTCP connection with the given address, using the options of
+    /// the builder.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address to connect to.
+    /// * `opt` - The MptcpOpt options for the connection.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing the MptcpSocket if the connection is successful,
+    /// or an `io::Error` if an error occurs during the connection.
+    fn connect<A>(
+        &self,
+        addr: A,
+        opt: MptcpOpt,
+    ) -> impl Future<Output = io::Result<MptcpSocket<TcpStream>>> + Send
+    where
+        A: ToSocketAddrs + Send + Sync,
+        A::Iter: Send;
+
+    /// Binds an MPTCP socket to the specified address, using the options of the builder.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address to bind the socket to.
+    /// * `opt` - The MptcpOpt to use for the socket.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing the MptcpSocket with the specified MptcpOpt.
+    fn bind<A>(
+        &self,
+        addr: A,
+        opt: MptcpOpt,
+    ) -> impl Future<Output = io::Result<MptcpSocket<TcpListener>>> + Send
+    where
+        A: ToSocketAddrs + Send + Sync,
+        A::Iter: Send;
+}
+
+async fn resolve_each_addr<A: ToSocketAddrs, F, Fut, T>(
+    addr: &A,
+    attempt: Attempt,
+    mut f: F,
+) -> io::Result<T>
+where
+    F: FnMut(SocketAddr) -> Fut,
+    Fut: Future<Output = io::Result<T>>,
+{
+    let addrs = attempt.resolve_async(addr.to_socket_addrs()).await?;
+    each_addr(addrs, |addr| attempt.run_async(addr, f(addr))).await
+}
+
+async fn each_addr<I, F, Fut, T>(addrs: I, mut f: F) -> io::Result<T>
 where
-    F: FnMut(SocketAddr) -> io::Result<T>,
+    I: IntoIterator<Item = SocketAddr>,
+    F: FnMut(SocketAddr) -> Fut,
+    Fut: Future<Output = io::Result<T>>,
 {
-    let addrs = addr.to_socket_addrs().await?;
     let mut last_err = None;
     for addr in addrs {
-        match f(addr) {
+        match f(addr).await {
             Ok(l) => return Ok(l),
             Err(e) => last_err = Some(e),
         }
@@ -129,31 +414,196 @@ where
     }))
 }
 
-#[async_trait::async_trait(?Send)]
-impl MptcpStreamExt for TcpStream {
-    type Output = Self;
+async fn connect_mptcp_addr(
+    builder: &MptcpSocketBuilder,
+    addr: SocketAddr,
+) -> io::Result<TcpStream> {
+    let sock = wait_connected(builder.connect_mptcp(addr, true)?).await?;
+    telemetry::connected(&sock);
+    Ok(sock)
+}
 
-    async fn connect_mptcp_opt<A: ToSocketAddrs>(
-        addr: A,
-        opt: MptcpOpt,
-    ) -> io::Result<MptcpSocket<Self::Output>> {
-        match resolve_each_addr(&addr, |addr| {
-            MptcpSocketBuilder::new_for_addr(addr)?
-                .set_nonblocking()?
-                .connect(addr)
-                .map::<std::net::TcpStream, _>(|s| s.into())
+async fn wait_connected(sock: Socket) -> io::Result<TcpStream> {
+    let sock = Async::new(std::net::TcpStream::from(sock))?;
+    // Wait for the socket to be writable
+    sock.writable().await?;
+    // A failed connection is also reported as writable
+    match sock.get_ref().take_error()? {
+        Some(err) => Err(err),
+        None => Ok(sock.into_inner()?.into()),
+    }
+}
+
+impl MptcpSocketBuilderExt for MptcpSocketBuilder {
+    async fn connect<A>(&self, addr: A, opt: MptcpOpt) -> io::Result<MptcpSocket<TcpStream>>
+    where
+        A: ToSocketAddrs + Send + Sync,
+        A::Iter: Send,
+    {
+        match resolve_each_addr(&addr, Attempt::mptcp("connect"), |addr| {
+            connect_mptcp_addr(self, addr)
+        })
+        .await
+        {
+            Ok(sock) => Ok(MptcpSocket::Mptcp(sock)),
+            Err(err) if matches!(opt, MptcpOpt::Fallback) => {
+                telemetry::fallback("connect", Fallback::Error(&err));
+                Ok(MptcpSocket::Tcp(
+                    resolve_each_addr(&addr, Attempt::fallback("connect"), |addr| async move {
+                        wait_connected(self.connect_tcp(addr, true)?).await
+                    })
+                    .await?,
+                ))
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    async fn bind<A>(&self, addr: A, opt: MptcpOpt) -> io::Result<MptcpSocket<TcpListener>>
+    where
+        A: ToSocketAddrs + Send + Sync,
+        A::Iter: Send,
+    {
+        match resolve_each_addr(&addr, Attempt::mptcp("bind"), |addr| async move {
+            self.bind_mptcp(addr, true)
+                .map::<std::net::TcpListener, _>(|s| s.into())
         })
         .await
         {
             Ok(sock) => Ok(MptcpSocket::Mptcp(sock.into())),
-            Err(_) if matches!(opt, MptcpOpt::Fallback) => {
-                Ok(MptcpSocket::Tcp(Self::connect(addr).await?))
+            Err(err) if matches!(opt, MptcpOpt::Fallback) => {
+                telemetry::fallback("bind", Fallback::Error(&err));
+                Ok(MptcpSocket::Tcp(
+                    resolve_each_addr(&addr, Attempt::fallback("bind"), |addr| async move {
+                        self.bind_tcp(addr, true)
+                            .map::<std::net::TcpListener, _>(|s| s.into())
+                    })
+                    .await?
+                    .into(),
+                ))
             }
             Err(err) => Err(err),
         }
     }
 }
 
+impl MptcpStreamExt for TcpStream {
+    type Output = Self;
+
+    async fn connect_mptcp_opt<A>(addr: A, opt: MptcpOpt) -> io::Result<MptcpSocket<Self::Output>>
+    where
+        A: ToSocketAddrs + Send + Sync,
+        A::Iter: Send,
+    {
+        MptcpSocketBuilder::new().connect(addr, opt).await
+    }
+
+    async fn connect_mptcp_race<A>(
+        addr: A,
+        delay: Duration,
+    ) -> io::Result<MptcpSocket<Self::Output>>
+    where
+        A: ToSocketAddrs + Send + Sync,
+        A::Iter: Send,
+    {
+        // Resolve once, so that both attempts use the same addresses
+        let (attempt, tcp) = (Attempt::mptcp("connect"), Attempt::tcp("connect"));
+        let builder = &MptcpSocketBuilder::new();
+        let addrs: Vec<_> = attempt
+            .resolve_async(addr.to_socket_addrs())
+            .await?
+            .collect();
+        race(
+            each_addr(addrs.iter().copied(), |addr| {
+                attempt.run_async(addr, connect_mptcp_addr(builder, addr))
+            }),
+            sleep(delay),
+            each_addr(addrs.iter().copied(), |addr| {
+                tcp.run_async(addr, Self::connect(addr))
+            }),
+        )
+        .await
+    }
+
+    async fn connect_mptcp_cached<A>(
+        addr: A,
+        cache: &FallbackCache,
+    ) -> io::Result<MptcpSocket<Self::Output>>
+    where
+        A: ToSocketAddrs + Send + Sync,
+        A::Iter: Send,
+    {
+        let addrs = Attempt::mptcp("connect")
+            .resolve_async(addr.to_socket_addrs())
+            .await?;
+        each_addr(addrs, |addr| async move {
+            if cache.contains(&addr) {
+                telemetry::fallback("connect", Fallback::Cached);
+            } else {
+                let attempt = Attempt::mptcp("connect");
+                match attempt
+                    .run_async(addr, connect_mptcp_addr(&MptcpSocketBuilder::new(), addr))
+                    .await
+                {
+                    Ok(sock) => {
+                        cache.record(addr, &sock);
+                        return Ok(MptcpSocket::Mptcp(sock));
+                    }
+                    Err(err) => telemetry::fallback("connect", Fallback::Error(&err)),
+                }
+            }
+            Attempt::fallback("connect")
+                .run_async(addr, Self::connect(addr))
+                .await
+                .map(MptcpSocket::Tcp)
+        })
+        .await
+    }
+
+    async fn connect_mptcp_from<A>(
+        local: SocketAddr,
+        addr: A,
+        opt: MptcpOpt,
+    ) -> io::Result<MptcpSocket<Self::Output>>
+    where
+        A: ToSocketAddrs + Send + Sync,
+        A::Iter: Send,
+    {
+        MptcpSocketBuilder::new()
+            .local_addr(local)
+            .connect(addr, opt)
+            .await
+    }
+
+    async fn connect_mptcp_from_device<A>(
+        interface: &str,
+        addr: A,
+        opt: MptcpOpt,
+    ) -> io::Result<MptcpSocket<Self::Output>>
+    where
+        A: ToSocketAddrs + Send + Sync,
+        A::Iter: Send,
+    {
+        MptcpSocketBuilder::new()
+            .bind_device(interface)
+            .connect(addr, opt)
+            .await
+    }
+
+    async fn connect_mptcp_with<A>(
+        addr: A,
+        options: ConnectOptions,
+    ) -> io::Result<MptcpSocket<Self::Output>>
+    where
+        A: ToSocketAddrs + Send + Sync,
+        A::Iter: Send,
+    {
+        MptcpSocketBuilder::from(options)
+            .connect(addr, MptcpOpt::Fallback)
+            .await
+    }
+}
+
 impl MptcpExt for TcpStream {}
 
 impl From<MptcpSocket<TcpStream>> for TcpStream {
@@ -162,31 +612,355 @@ impl From<MptcpSocket<TcpStream>> for TcpStream {
     }
 }
 
-#[async_trait::async_trait(?Send)]
+/// A `TcpStream` remembering whether it uses MPTCP.
+///
+/// Unlike `MptcpSocket<TcpStream>`, it implements `Read` and `Write`, so it can be
+/// passed to generic code while keeping the MPTCP metadata. The status is checked
+/// when the stream is created: use `MptcpExt::mptcp_status` to detect a later fallback.
+///
+/// Like `TcpStream`, the stream can be cloned to read and write from different tasks,
+/// the clones keeping the MPTCP status.
+#[derive(Debug, Clone)]
+pub struct MptcpStream {
+    inner: TcpStream,
+    status: MptcpStatus,
+}
+
+impl MptcpStream {
+    /// Returns the MPTCP status of the stream when it was created.
+    pub fn status(&self) -> MptcpStatus {
+        self.status
+    }
+
+    /// Returns whether the stream used MPTCP without fallback when it was created.
+    pub fn is_mptcp(&self) -> bool {
+        self.status.is_mptcp()
+    }
+
+    /// Returns information about the MPTCP connection, see `MptcpExt::mptcp_info`.
+    pub fn info(&self) -> io::Result<MptcpInfo> {
+        self.inner.mptcp_info()
+    }
+
+    /// Returns the addresses of the subflows, see `MptcpExt::mptcp_subflows`.
+    pub fn subflows(&self) -> io::Result<Vec<MptcpSubflow>> {
+        self.inner.mptcp_subflows()
+    }
+
+    /// Waits until at least `n` subflows are established, including the initial one.
+    ///
+    /// On Linux, path manager events are used when the process can subscribe to them
+    /// (this requires `CAP_NET_ADMIN` on recent kernels), otherwise `MPTCP_INFO` is
+    /// polled.
+    ///
+    /// # Arguments
+    ///
+    /// * `n` - The number of subflows to wait for.
+    /// * `timeout` - The maximum time to wait.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing the number of established subflows, or an
+    /// `io::Error` of kind `io::ErrorKind::TimedOut` if they were not established in
+    /// time. An error is also returned if the stream does not use MPTCP.
+    pub async fn wait_for_subflows(&self, n: usize, timeout: Duration) -> io::Result<usize> {
+        let deadline = Instant::now() + timeout;
+        let events = subscribe_pm_events();
+
+        loop {
+            let info = self.info()?;
+            let subflows = info.subflows as usize + 1;
+            if subflows >= n {
+                return Ok(subflows);
+            }
+
+            let changed = async {
+                match &events {
+                    Some(events) => {
+                        wait_pm_event(events, info.token).await;
+                    }
+                    None => sleep(POLL_INTERVAL).await,
+                }
+            };
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if future::timeout(remaining, changed).await.is_err() {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "timed out waiting for subflows",
+                ));
+            }
+        }
+    }
+
+    /// Waits until the connection falls back to TCP.
+    ///
+    /// An established MPTCP connection can fall back to TCP, e.g. when a middlebox
+    /// corrupts the DSS checksum. The `MPTCP_INFO` flags are checked periodically and
+    /// when a path manager event is received for the connection (subscribing to them
+    /// requires `CAP_NET_ADMIN` on recent kernels). The returned future can be raced
+    /// against the I/O on the stream, or spawned with a callback.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` that is `Ok` once the connection falls back, or
+    /// immediately if it already did. An `io::Error` of kind
+    /// `io::ErrorKind::NotConnected` is returned if the connection is closed without
+    /// falling back, and an error is also returned if the stream did not use MPTCP
+    /// or `MPTCP_INFO` is not supported.
+    pub async fn wait_for_fallback(&self) -> io::Result<()> {
+        let events = subscribe_pm_events();
+        let events = events.as_ref();
+
+        event::wait_for_fallback(&self.inner, self.status, |token| async move {
+            match events {
+                Some(events) => future::timeout(POLL_INTERVAL, wait_pm_event(events, token))
+                    .await
+                    .unwrap_or(PmUpdate::Unrelated),
+                None => {
+                    sleep(POLL_INTERVAL).await;
+                    PmUpdate::Unrelated
+                }
+            }
+        })
+        .await
+    }
+
+    /// Returns a reference to the underlying `TcpStream`.
+    pub fn get_ref(&self) -> &TcpStream {
+        &self.inner
+    }
+
+    /// Consumes the stream, returning the underlying `TcpStream`.
+    pub fn into_inner(self) -> TcpStream {
+        self.inner
+    }
+}
+
+/// Subscribes to the path manager events, registering the socket with the reactor.
+#[cfg(target_os = "linux")]
+fn subscribe_pm_events() -> Option<Async<crate::sys::PmEvents>> {
+    crate::sys::PmEvents::subscribe().and_then(Async::new).ok()
+}
+
+/// Path manager events are only sent by Linux, `MPTCP_INFO` is polled elsewhere.
+#[cfg(not(target_os = "linux"))]
+fn subscribe_pm_events() -> Option<Infallible> {
+    None
+}
+
+/// Waits for a path manager event about the connection with the given token.
+///
+/// Returns how the events changed the connection, `PmUpdate::Unrelated` if they
+/// could not be received.
+#[cfg(target_os = "linux")]
+async fn wait_pm_event(events: &Async<crate::sys::PmEvents>, token: u32) -> PmUpdate {
+    loop {
+        match events.read_with(|events| events.recv()).await {
+            Ok(events) => {
+                let update = PmUpdate::from_events(&events, token);
+                if update != PmUpdate::Unrelated {
+                    return update;
+                }
+            }
+            // Events may have been lost, e.g. ENOBUFS
+            Err(_) => {
+                sleep(POLL_INTERVAL).await;
+                return PmUpdate::Unrelated;
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+async fn wait_pm_event(events: &Infallible, _token: u32) -> PmUpdate {
+    match *events {}
+}
+
+impl From<TcpStream> for MptcpStream {
+    fn from(stream: TcpStream) -> Self {
+        let status = stream.mptcp_status();
+        Self {
+            inner: stream,
+            status,
+        }
+    }
+}
+
+impl From<MptcpSocket<TcpStream>> for MptcpStream {
+    fn from(socket: MptcpSocket<TcpStream>) -> Self {
+        socket.into_socket().into()
+    }
+}
+
+impl From<MptcpStream> for TcpStream {
+    fn from(stream: MptcpStream) -> Self {
+        stream.inner
+    }
+}
+
+impl Read for MptcpStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+
+    fn poll_read_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &mut [IoSliceMut<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_read_vectored(cx, bufs)
+    }
+}
+
+impl Write for MptcpStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write_vectored(cx, bufs)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_close(cx)
+    }
+}
+
+impl Read for &MptcpStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut &self.inner).poll_read(cx, buf)
+    }
+
+    fn poll_read_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &mut [IoSliceMut<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut &self.inner).poll_read_vectored(cx, bufs)
+    }
+}
+
+impl Write for &MptcpStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut &self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut &self.inner).poll_write_vectored(cx, bufs)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut &self.inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut &self.inner).poll_close(cx)
+    }
+}
+
+impl AsRawFd for MptcpStream {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+impl MptcpExt for MptcpStream {}
+
 impl MptcpListenerExt for TcpListener {
     type Output = Self;
 
-    async fn bind_mptcp_opt<A: ToSocketAddrs>(
+    async fn bind_mptcp_opt<A>(addr: A, opt: MptcpOpt) -> io::Result<MptcpSocket<Self::Output>>
+    where
+        A: ToSocketAddrs + Send + Sync,
+        A::Iter: Send,
+    {
+        MptcpSocketBuilder::new().bind(addr, opt).await
+    }
+
+    async fn bind_mptcp_with<A>(
         addr: A,
-        opt: MptcpOpt,
-    ) -> io::Result<MptcpSocket<Self::Output>> {
-        match resolve_each_addr(&addr, |addr| {
-            MptcpSocketBuilder::new_for_addr(addr)?
-                .set_nonblocking()?
-                .bind(addr)
-                .map::<std::net::TcpListener, _>(|s| s.into())
-        })
-        .await
-        {
-            Ok(sock) => Ok(MptcpSocket::Mptcp(sock.into())),
-            Err(_) if matches!(opt, MptcpOpt::Fallback) => {
-                Ok(MptcpSocket::Tcp(Self::bind(addr).await?))
+        options: ListenOptions,
+    ) -> io::Result<MptcpSocket<Self::Output>>
+    where
+        A: ToSocketAddrs + Send + Sync,
+        A::Iter: Send,
+    {
+        MptcpSocketBuilder::from(options)
+            .bind(addr, MptcpOpt::Fallback)
+            .await
+    }
+
+    async fn accept_mptcp(&self) -> io::Result<(MptcpSocket<TcpStream>, SocketAddr)> {
+        let (sock, addr) = self.accept().await?;
+        Ok((MptcpSocket::from_accepted(sock), addr))
+    }
+
+    async fn accept_mptcp_filtered(
+        &self,
+        filter: &AcceptFilter<TcpStream>,
+    ) -> io::Result<(TcpStream, SocketAddr)> {
+        filter.check_supported()?;
+        loop {
+            let (sock, addr) = self.accept().await?;
+            if let Some(sock) = filter.filter(sock, addr) {
+                return Ok((sock, addr));
             }
-            Err(err) => Err(err),
+        }
+    }
+
+    fn incoming_mptcp(&self) -> IncomingMptcp<'_> {
+        IncomingMptcp {
+            incoming: self.incoming(),
         }
     }
 }
 
+impl MptcpExt for TcpListener {}
+
+/// A stream of the connections of a `TcpListener`, see `incoming_mptcp`.
+#[derive(Debug)]
+pub struct IncomingMptcp<'a> {
+    incoming: Incoming<'a>,
+}
+
+impl Stream for IncomingMptcp<'_> {
+    type Item = io::Result<MptcpSocket<TcpStream>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.incoming)
+            .poll_next(cx)
+            .map(|res| res.map(|res| res.map(MptcpSocket::from_accepted)))
+    }
+}
+
 impl From<MptcpSocket<TcpListener>> for TcpListener {
     fn from(socket: MptcpSocket<TcpListener>) -> Self {
         socket.into_socket()
@@ -197,14 +971,81 @@ impl From<MptcpSocket<TcpListener>> for TcpListener {
 mod tests {
     use super::*;
 
-    use std::net::{IpAddr, Ipv4Addr};
+    use std::{
+        net::{IpAddr, Ipv4Addr},
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    use crate::{
+        sys::{has_mptcp_info, is_mptcp_enabled},
+        MptcpStatus,
+    };
+
+    fn assert_send<T: Send>(_: T) {}
+
+    // Only compiles if the futures are `Send` for any implementor of the traits,
+    // not only for the types of this module.
+    fn assert_generic_send<S, L, B>(addr: SocketAddr, listener: &L, builder: &B)
+    where
+        S: MptcpStreamExt,
+        L: MptcpListenerExt,
+        B: MptcpSocketBuilderExt,
+    {
+        assert_send(S::connect_mptcp(addr));
+        assert_send(S::connect_mptcp_force(addr));
+        assert_send(S::connect_mptcp_race(addr, Duration::from_millis(10)));
+        assert_send(S::connect_mptcp_with(addr, ConnectOptions::default()));
+        assert_send(L::bind_mptcp(addr));
+        assert_send(L::bind_mptcp_force(addr));
+        assert_send(L::bind_mptcp_with(addr, ListenOptions::default()));
+        assert_send(listener.accept_mptcp());
+        assert_send(builder.connect(addr, MptcpOpt::Fallback));
+        assert_send(builder.bind(addr, MptcpOpt::Fallback));
+    }
+
+    #[test]
+    fn test_futures_are_send() {
+        let _ = assert_generic_send::<TcpStream, TcpListener, MptcpSocketBuilder>;
+        let addr = "127.0.0.1:0";
+        let cache = FallbackCache::new(16, Duration::from_secs(60));
+        let builder = MptcpSocketBuilder::new();
 
-    use crate::sys::is_mptcp_enabled;
+        assert_send(TcpStream::connect_mptcp(addr));
+        assert_send(TcpStream::connect_mptcp_opt(addr, MptcpOpt::NoFallback));
+        assert_send(TcpStream::connect_mptcp_force(addr));
+        assert_send(TcpStream::connect_mptcp_race(
+            addr,
+            Duration::from_millis(10),
+        ));
+        assert_send(TcpStream::connect_mptcp_cached(addr, &cache));
+        assert_send(TcpStream::connect_mptcp_from(
+            "127.0.0.1:0".parse().unwrap(),
+            addr,
+            MptcpOpt::Fallback,
+        ));
+        assert_send(TcpStream::connect_mptcp_from_device(
+            "lo",
+            addr,
+            MptcpOpt::Fallback,
+        ));
+        assert_send(TcpListener::bind_mptcp(addr));
+        assert_send(TcpListener::bind_mptcp_force(addr));
+        assert_send(TcpStream::connect_mptcp_with(
+            addr,
+            ConnectOptions::default(),
+        ));
+        assert_send(TcpListener::bind_mptcp_with(addr, ListenOptions::default()));
+        assert_send(builder.connect(addr, MptcpOpt::Fallback));
+        assert_send(builder.bind(addr, MptcpOpt::Fallback));
+    }
 
     #[tokio::test]
     async fn test_resolve_each_addr() {
         let addr = "127.0.0.1:80";
-        let result = resolve_each_addr(&addr, |addr| {
+        let result = resolve_each_addr(&addr, Attempt::mptcp("connect"), |addr| async move {
             assert_eq!(addr.port(), 80);
             assert_eq!(addr.ip(), IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)));
             Ok(())
@@ -216,7 +1057,8 @@ mod tests {
     #[tokio::test]
     async fn test_resolve_each_addr_error() {
         let addr = "thisisanerror";
-        let result = resolve_each_addr(&addr, |_| Ok(())).await;
+        let result =
+            resolve_each_addr(&addr, Attempt::mptcp("connect"), |_| async { Ok(()) }).await;
         assert!(result.is_err());
     }
 
@@ -242,6 +1084,185 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_mptcp_listener_status() {
+        let mptcp_enabled = is_mptcp_enabled();
+
+        let listener = TcpListener::bind_mptcp("127.0.0.1:0").await.unwrap();
+        if mptcp_enabled {
+            assert!(matches!(
+                listener.mptcp_status(),
+                MptcpStatus::Mptcp {
+                    has_fallback: false
+                }
+            ));
+        } else {
+            assert!(matches!(listener.mptcp_status(), MptcpStatus::Tcp));
+        }
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        assert!(matches!(listener.mptcp_status(), MptcpStatus::Tcp));
+    }
+
+    #[tokio::test]
+    async fn test_accept_mptcp_filtered() {
+        if !is_mptcp_enabled() || !has_mptcp_info() {
+            // Fallback can only be detected on >= 5.16 kernels
+            return;
+        }
+
+        let listener = TcpListener::bind_mptcp("127.0.0.1:0").await.unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        let rejected = Arc::new(AtomicUsize::new(0));
+        let counter = rejected.clone();
+        let filter = AcceptFilter::with_handler(move |_, _| {
+            counter.fetch_add(1, Ordering::Relaxed);
+        });
+
+        let _tcp = TcpStream::connect(local_addr).await.unwrap();
+        let mptcp = TcpStream::connect_mptcp(local_addr).await.unwrap();
+
+        let (accepted, addr) = listener.accept_mptcp_filtered(&filter).await.unwrap();
+        assert_eq!(addr, mptcp.local_addr().unwrap());
+        assert!(matches!(
+            accepted.mptcp_status(),
+            MptcpStatus::Mptcp {
+                has_fallback: false
+            }
+        ));
+        assert_eq!(filter.rejected(), 1);
+        assert_eq!(rejected.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn test_accept_mptcp() {
+        let mptcp_enabled = is_mptcp_enabled();
+
+        let listener = TcpListener::bind_mptcp("127.0.0.1:0").await.unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        let stream = TcpStream::connect_mptcp(local_addr).await.unwrap();
+        let (accepted, addr) = listener.accept_mptcp().await.unwrap();
+        assert_eq!(addr, stream.local_addr().unwrap());
+        if mptcp_enabled {
+            assert!(matches!(accepted, MptcpSocket::Mptcp(..)));
+        } else {
+            assert!(matches!(accepted, MptcpSocket::Tcp(..)));
+        }
+
+        let _stream = TcpStream::connect(local_addr).await.unwrap();
+        let (accepted, _) = listener.accept_mptcp().await.unwrap();
+        if !mptcp_enabled || has_mptcp_info() {
+            // Fallback can only be detected on >= 5.16 kernels
+            assert!(matches!(accepted, MptcpSocket::Tcp(..)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_incoming_mptcp() {
+        use async_std::stream::StreamExt;
+
+        let mptcp_enabled = is_mptcp_enabled();
+
+        let listener = TcpListener::bind_mptcp("127.0.0.1:0").await.unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        let _stream = TcpStream::connect_mptcp(local_addr).await.unwrap();
+
+        let accepted = listener.incoming_mptcp().next().await.unwrap().unwrap();
+        assert_eq!(matches!(accepted, MptcpSocket::Mptcp(..)), mptcp_enabled);
+    }
+
+    #[tokio::test]
+    async fn test_mptcp_stream() {
+        use async_std::io::{ReadExt, WriteExt};
+
+        let mptcp_enabled = is_mptcp_enabled();
+
+        let listener = TcpListener::bind_mptcp("127.0.0.1:0").await.unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        let mut stream = MptcpStream::from(TcpStream::connect_mptcp(local_addr).await.unwrap());
+        let (accepted, _) = listener.accept().await.unwrap();
+        let accepted = MptcpStream::from(accepted);
+        assert_eq!(stream.is_mptcp(), mptcp_enabled);
+        assert_eq!(accepted.is_mptcp(), mptcp_enabled);
+        if mptcp_enabled && has_mptcp_info() {
+            assert!(stream.info().is_ok());
+            assert_eq!(stream.subflows().unwrap().len(), 1);
+        }
+
+        let clone = accepted.clone();
+        assert_eq!(clone.status(), accepted.status());
+        async_std::task::spawn(async move {
+            let mut buf = [0; 5];
+            (&accepted).read_exact(&mut buf).await.unwrap();
+            (&clone).write_all(&buf).await.unwrap();
+        });
+
+        stream.write_all(b"hello").await.unwrap();
+        let mut buf = [0; 5];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello");
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_subflows() {
+        if !is_mptcp_enabled() || !has_mptcp_info() {
+            return;
+        }
+
+        let listener = TcpListener::bind_mptcp("127.0.0.1:0").await.unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        let stream = MptcpStream::from(TcpStream::connect_mptcp(local_addr).await.unwrap());
+        let _accepted = listener.accept().await.unwrap();
+
+        assert_eq!(
+            stream
+                .wait_for_subflows(1, Duration::from_millis(100))
+                .await
+                .unwrap(),
+            1
+        );
+
+        // Additional subflows depend on the path manager configuration
+        let res = stream
+            .wait_for_subflows(8, Duration::from_millis(100))
+            .await;
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_fallback() {
+        if !is_mptcp_enabled() || !has_mptcp_info() {
+            return;
+        }
+
+        let listener = TcpListener::bind_mptcp("127.0.0.1:0").await.unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        let tcp = MptcpStream::from(TcpStream::connect(local_addr).await.unwrap());
+        let res = tcp.wait_for_fallback().await;
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+
+        // MPTCP clients fall back when the server does not use MPTCP
+        let tcp_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let tcp_addr = tcp_listener.local_addr().unwrap();
+        let fallback = MptcpStream::from(TcpStream::connect_mptcp(tcp_addr).await.unwrap());
+        let _accepted = tcp_listener.accept().await.unwrap();
+        fallback.wait_for_fallback().await.unwrap();
+
+        let stream = MptcpStream::from(TcpStream::connect_mptcp(local_addr).await.unwrap());
+        let _accepted = listener.accept().await.unwrap();
+        assert!(
+            future::timeout(Duration::from_millis(100), stream.wait_for_fallback())
+                .await
+                .is_err()
+        );
+    }
+
     #[tokio::test]
     async fn test_mptcp_no_fallback() {
         let mptcp_enabled = is_mptcp_enabled();
@@ -257,4 +1278,172 @@ mod tests {
         let stream = TcpStream::connect_mptcp_force("127.0.0.1:0").await;
         assert!(stream.is_err());
     }
+
+    #[tokio::test]
+    async fn test_mptcp_refused() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let local_addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        let stream = TcpStream::connect_mptcp_force(local_addr).await;
+        assert!(stream.is_err());
+
+        let stream = TcpStream::connect_mptcp(local_addr).await;
+        assert!(stream.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fast_open() {
+        use async_std::io::{ReadExt, WriteExt};
+
+        let mptcp_enabled = is_mptcp_enabled();
+        let options = ListenOptions {
+            fast_open: true,
+            ..Default::default()
+        };
+
+        let listener = TcpListener::bind_mptcp_with("127.0.0.1:0", options)
+            .await
+            .unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        let options = ConnectOptions {
+            fast_open: true,
+            ..Default::default()
+        };
+        let mut stream = TcpStream::connect_mptcp_with(local_addr, options)
+            .await
+            .unwrap();
+        if mptcp_enabled {
+            assert!(matches!(stream, MptcpSocket::Mptcp(..)));
+        } else {
+            assert!(matches!(stream, MptcpSocket::Tcp(..)));
+        }
+        stream.write_all(b"hello").await.unwrap();
+
+        let (mut accepted, _) = listener.accept().await.unwrap();
+        let mut buf = [0; 5];
+        accepted.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello");
+    }
+
+    #[tokio::test]
+    async fn test_bind_mptcp_with() {
+        let mptcp_enabled = is_mptcp_enabled();
+        let options = ListenOptions {
+            backlog: 4096,
+            reuse_port: true,
+            ..Default::default()
+        };
+
+        let listener = TcpListener::bind_mptcp_with("127.0.0.1:0", options.clone()).await;
+        if mptcp_enabled {
+            assert!(matches!(listener, Ok(MptcpSocket::Mptcp(..))));
+        } else {
+            assert!(matches!(listener, Ok(MptcpSocket::Tcp(..))));
+        }
+
+        let local_addr = listener.unwrap().local_addr().unwrap();
+        let other = TcpListener::bind_mptcp_with(local_addr, options)
+            .await
+            .unwrap();
+        assert_eq!(other.local_addr().unwrap(), local_addr);
+    }
+
+    #[tokio::test]
+    async fn test_mptcp_from() {
+        let mptcp_enabled = is_mptcp_enabled();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        let stream = TcpStream::connect_mptcp_from(
+            "127.0.0.2:0".parse().unwrap(),
+            local_addr,
+            MptcpOpt::Fallback,
+        )
+        .await;
+        if mptcp_enabled {
+            assert!(matches!(stream, Ok(MptcpSocket::Mptcp(..))));
+        } else {
+            assert!(matches!(stream, Ok(MptcpSocket::Tcp(..))));
+        }
+        assert_eq!(
+            stream.unwrap().local_addr().unwrap().ip(),
+            IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mptcp_from_device() {
+        let mptcp_enabled = is_mptcp_enabled();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        let stream =
+            TcpStream::connect_mptcp_from_device("lo", local_addr, MptcpOpt::Fallback).await;
+        if mptcp_enabled {
+            assert!(matches!(stream, Ok(MptcpSocket::Mptcp(..))));
+        } else {
+            assert!(matches!(stream, Ok(MptcpSocket::Tcp(..))));
+        }
+
+        let stream =
+            TcpStream::connect_mptcp_from_device("nonexistent0", local_addr, MptcpOpt::Fallback)
+                .await;
+        assert!(stream.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mptcp_cached() {
+        let mptcp_enabled = is_mptcp_enabled();
+        let cache = FallbackCache::new(16, Duration::from_secs(60));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        let stream = TcpStream::connect_mptcp_cached(local_addr, &cache)
+            .await
+            .unwrap();
+        if !mptcp_enabled || !has_mptcp_info() {
+            // Fallback can only be detected on >= 5.16 kernels
+            return;
+        }
+        assert!(matches!(stream, MptcpSocket::Mptcp(..)));
+        assert!(cache.contains(&local_addr));
+
+        let stream = TcpStream::connect_mptcp_cached(local_addr, &cache)
+            .await
+            .unwrap();
+        assert!(matches!(stream, MptcpSocket::Tcp(..)));
+    }
+
+    #[tokio::test]
+    async fn test_mptcp_race() {
+        let mptcp_enabled = is_mptcp_enabled();
+
+        let listener = TcpListener::bind_mptcp("127.0.0.1:0")
+            .await
+            .unwrap()
+            .into_socket();
+        let local_addr = listener.local_addr().unwrap();
+
+        let stream = TcpStream::connect_mptcp_race(local_addr, Duration::from_secs(1)).await;
+        if mptcp_enabled {
+            assert!(matches!(stream, Ok(MptcpSocket::Mptcp(..))));
+        } else {
+            assert!(matches!(stream, Ok(MptcpSocket::Tcp(..))));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mptcp_race_refused() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let local_addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        let stream = TcpStream::connect_mptcp_race(local_addr, Duration::from_secs(1)).await;
+        assert!(stream.is_err());
+    }
 }
@@ -0,0 +1,161 @@
+use std::{io, net::SocketAddr, time::Duration};
+
+use axum::{
+    extract::connect_info::Connected,
+    serve::{IncomingStream, Listener},
+};
+use tokio::{net::TcpListener, time::sleep};
+
+use crate::{
+    tokio::{MptcpConnectionInfo, MptcpListenerExt, MptcpStream},
+    MptcpExt, MptcpSocket,
+};
+
+/// Serves the connections of an MPTCP listener with `axum::serve`.
+///
+/// The accepted connections are `MptcpStream`s, so handlers can learn whether the
+/// client uses MPTCP with `ConnectInfo<MptcpConnectionInfo>`. As with the tokio
+/// `TcpListener`, accept errors are retried, after one second if they are not
+/// connection errors.
+///
+/// A plain `TcpListener`, e.g. from `bind_mptcp_force` or `MptcpSocket::into_socket`,
+/// is served by axum itself: `ConnectInfo<MptcpConnectionInfo>` is also available
+/// with it, but the accepted connections are not reported by the `metrics` feature.
+impl Listener for MptcpSocket<TcpListener> {
+    type Io = MptcpStream;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        loop {
+            match self.accept_mptcp().await {
+                Ok((sock, addr)) => return (sock.into(), addr),
+                Err(err) => handle_accept_error(err).await,
+            }
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        TcpListener::local_addr(self)
+    }
+}
+
+async fn handle_accept_error(err: io::Error) {
+    if matches!(
+        err.kind(),
+        io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::ConnectionReset
+    ) {
+        return;
+    }
+
+    // e.g. EMFILE: wait for some connections to be closed
+    #[cfg(feature = "tracing")]
+    tracing::error!("accept error: {err}");
+    #[cfg(not(feature = "tracing"))]
+    let _ = err;
+    sleep(Duration::from_secs(1)).await;
+}
+
+impl Connected<IncomingStream<'_, MptcpSocket<TcpListener>>> for MptcpConnectionInfo {
+    fn connect_info(stream: IncomingStream<'_, MptcpSocket<TcpListener>>) -> Self {
+        Self::new(stream.io(), *stream.remote_addr())
+    }
+}
+
+impl Connected<IncomingStream<'_, TcpListener>> for MptcpConnectionInfo {
+    fn connect_info(stream: IncomingStream<'_, TcpListener>) -> Self {
+        Self {
+            remote_addr: *stream.remote_addr(),
+            status: stream.io().mptcp_status(),
+        }
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    use axum::{extract::ConnectInfo, routing::get, Router};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+
+    use crate::{sys::is_mptcp_enabled, tokio::MptcpStreamExt};
+
+    async fn get_body(stream: &mut TcpStream) -> String {
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response.split("\r\n\r\n").nth(1).unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_serve() {
+        let mptcp_enabled = is_mptcp_enabled();
+
+        let app = Router::new().route(
+            "/",
+            get(
+                |ConnectInfo(info): ConnectInfo<MptcpConnectionInfo>| async move {
+                    format!("{} {}", info.remote_addr, info.is_mptcp())
+                },
+            ),
+        );
+        let listener = TcpListener::bind_mptcp("127.0.0.1:0").await.unwrap();
+        let local_addr = Listener::local_addr(&listener).unwrap();
+        tokio::spawn(async move {
+            axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<MptcpConnectionInfo>(),
+            )
+            .await
+            .unwrap();
+        });
+
+        let mut stream = TcpStream::connect_mptcp(local_addr).await.unwrap();
+        let body = get_body(&mut stream).await;
+        assert_eq!(
+            body,
+            format!("{} {}", stream.local_addr().unwrap(), mptcp_enabled)
+        );
+
+        let mut stream = TcpStream::connect(local_addr).await.unwrap();
+        let body = get_body(&mut stream).await;
+        assert_eq!(body, format!("{} false", stream.local_addr().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_serve_plain() {
+        let mptcp_enabled = is_mptcp_enabled();
+
+        let app = Router::new().route(
+            "/",
+            get(
+                |ConnectInfo(info): ConnectInfo<MptcpConnectionInfo>| async move {
+                    info.is_mptcp().to_string()
+                },
+            ),
+        );
+        let listener = TcpListener::bind_mptcp("127.0.0.1:0")
+            .await
+            .unwrap()
+            .into_socket();
+        let local_addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<MptcpConnectionInfo>(),
+            )
+            .await
+            .unwrap();
+        });
+
+        let mut stream = TcpStream::connect_mptcp(local_addr).await.unwrap();
+        assert_eq!(get_body(&mut stream).await, mptcp_enabled.to_string());
+    }
+}
@@ -0,0 +1,442 @@
+use std::{
+    fmt, io,
+    net::{Ipv6Addr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use socket2::{Domain, Protocol, Socket, TcpKeepalive, Type};
+
+use crate::sys;
+
+/// A builder to configure MPTCP sockets before connecting or binding them.
+///
+/// The same options are applied to the TCP socket used in case of fallback. The
+/// builder does not create any socket by itself: use the `MptcpSocketBuilderExt`
+/// trait of the runtime (std, `tokio` or `async_std`) to connect or bind.
+///
+/// # Example
+///
+/// ```rust
+/// use mptcp::{MptcpOpt, MptcpSocketBuilder, MptcpSocketBuilderExt};
+///
+/// let listener = MptcpSocketBuilder::new()
+///     .reuse_port(true)
+///     .backlog(1024)
+///     .bind("127.0.0.1:0", MptcpOpt::Fallback)
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct MptcpSocketBuilder {
+    reuse_address: Option<bool>,
+    reuse_port: Option<bool>,
+    backlog: i32,
+    only_v6: Option<bool>,
+    device: Option<String>,
+    keepalive: Option<Duration>,
+    nodelay: Option<bool>,
+    send_buffer_size: Option<usize>,
+    recv_buffer_size: Option<usize>,
+    local_addr: Option<SocketAddr>,
+    fast_open: bool,
+    hook: Option<SocketHook>,
+}
+
+type SocketHookFn = dyn Fn(&Socket) -> io::Result<()> + Send + Sync;
+
+#[derive(Clone)]
+struct SocketHook(Arc<SocketHookFn>);
+
+impl fmt::Debug for SocketHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SocketHook")
+    }
+}
+
+impl Default for MptcpSocketBuilder {
+    fn default() -> Self {
+        Self {
+            reuse_address: None,
+            reuse_port: None,
+            backlog: 128,
+            only_v6: None,
+            device: None,
+            keepalive: None,
+            nodelay: None,
+            send_buffer_size: None,
+            recv_buffer_size: None,
+            local_addr: None,
+            fast_open: false,
+            hook: None,
+        }
+    }
+}
+
+impl MptcpSocketBuilder {
+    /// Creates a builder with the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets `SO_REUSEADDR` on the socket.
+    ///
+    /// Listeners enable it by default, like `std::net::TcpListener::bind`.
+    pub fn reuse_address(mut self, reuse: bool) -> Self {
+        self.reuse_address = Some(reuse);
+        self
+    }
+
+    /// Sets `SO_REUSEPORT` on the socket.
+    pub fn reuse_port(mut self, reuse: bool) -> Self {
+        self.reuse_port = Some(reuse);
+        self
+    }
+
+    /// Sets the maximum length of the queue of pending connections of listeners.
+    ///
+    /// Defaults to 128, like `std::net::TcpListener::bind`.
+    pub fn backlog(mut self, backlog: i32) -> Self {
+        self.backlog = backlog;
+        self
+    }
+
+    /// Sets `IPV6_V6ONLY` on the socket. Ignored for IPv4 addresses.
+    ///
+    /// Listeners bound to the unspecified IPv6 address (`[::]`) disable it by default,
+    /// so that they accept both IPv4 and IPv6 connections whatever the system setting.
+    /// IPv4 peers are then reported as IPv4-mapped IPv6 addresses.
+    pub fn only_v6(mut self, only_v6: bool) -> Self {
+        self.only_v6 = Some(only_v6);
+        self
+    }
+
+    /// Binds the socket to the given network interface using `SO_BINDTODEVICE`.
+    ///
+    /// Only supported on Linux.
+    pub fn bind_device(mut self, interface: &str) -> Self {
+        self.device = Some(interface.to_owned());
+        self
+    }
+
+    /// Enables TCP keepalive, sending probes after the connection has been idle
+    /// for `idle`.
+    pub fn keepalive(mut self, idle: Duration) -> Self {
+        self.keepalive = Some(idle);
+        self
+    }
+
+    /// Sets `TCP_NODELAY` on the socket.
+    pub fn nodelay(mut self, nodelay: bool) -> Self {
+        self.nodelay = Some(nodelay);
+        self
+    }
+
+    /// Sets the size of the send buffer (`SO_SNDBUF`) of the socket.
+    pub fn send_buffer_size(mut self, size: usize) -> Self {
+        self.send_buffer_size = Some(size);
+        self
+    }
+
+    /// Sets the size of the receive buffer (`SO_RCVBUF`) of the socket.
+    pub fn recv_buffer_size(mut self, size: usize) -> Self {
+        self.recv_buffer_size = Some(size);
+        self
+    }
+
+    /// Binds connecting sockets to the given local address before connecting.
+    ///
+    /// Ignored by listeners.
+    pub fn local_addr(mut self, addr: SocketAddr) -> Self {
+        self.local_addr = Some(addr);
+        self
+    }
+
+    /// Enables TCP Fast Open, so that the first bytes sent ride in the SYN.
+    ///
+    /// On connecting sockets, this sets `TCP_FASTOPEN_CONNECT`: connecting returns
+    /// immediately when a TFO cookie for the destination is known, the SYN being sent
+    /// with the first write. Connection errors are then only reported by that write,
+    /// and do not trigger the fallback to TCP. On listeners, this sets `TCP_FASTOPEN`
+    /// with the backlog as maximum number of pending TFO requests.
+    ///
+    /// TFO must also be enabled by the `net.ipv4.tcp_fastopen` sysctl. MPTCP supports
+    /// it since Linux 6.2, older kernels fall back to TCP. Only supported on Linux.
+    pub fn fast_open(mut self, enabled: bool) -> Self {
+        self.fast_open = enabled;
+        self
+    }
+
+    /// Calls `hook` on the socket once the options of the builder are applied, right
+    /// before it is bound or connected.
+    ///
+    /// This allows setting socket options the builder does not know about, such as
+    /// `SO_MARK` or `TCP_CONGESTION`. The hook is called on the TCP socket too in case
+    /// of fallback, and on each socket of sharded listeners. An error returned by the
+    /// hook aborts the attempt with that socket.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use mptcp::{MptcpOpt, MptcpSocketBuilder, MptcpSocketBuilderExt};
+    ///
+    /// let listener = MptcpSocketBuilder::new()
+    ///     .on_socket(|socket| socket.set_tos(0x10))
+    ///     .bind("127.0.0.1:0", MptcpOpt::Fallback)
+    ///     .unwrap();
+    /// ```
+    pub fn on_socket<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&Socket) -> io::Result<()> + Send + Sync + 'static,
+    {
+        self.hook = Some(SocketHook(Arc::new(hook)));
+        self
+    }
+
+    fn configure(&self, socket: &Socket, addr: SocketAddr) -> io::Result<()> {
+        if let Some(reuse) = self.reuse_address {
+            socket.set_reuse_address(reuse)?;
+        }
+        if let Some(reuse) = self.reuse_port {
+            socket.set_reuse_port(reuse)?;
+        }
+        if let Some(only_v6) = self.only_v6 {
+            if addr.is_ipv6() {
+                socket.set_only_v6(only_v6)?;
+            }
+        }
+        if let Some(device) = &self.device {
+            bind_device(socket, device)?;
+        }
+        if let Some(idle) = self.keepalive {
+            socket.set_tcp_keepalive(&TcpKeepalive::new().with_time(idle))?;
+        }
+        if let Some(nodelay) = self.nodelay {
+            socket.set_nodelay(nodelay)?;
+        }
+        if let Some(size) = self.send_buffer_size {
+            socket.set_send_buffer_size(size)?;
+        }
+        if let Some(size) = self.recv_buffer_size {
+            socket.set_recv_buffer_size(size)?;
+        }
+        if let Some(SocketHook(hook)) = &self.hook {
+            hook(socket)?;
+        }
+        Ok(())
+    }
+
+    fn configure_connect(&self, socket: &Socket, addr: SocketAddr) -> io::Result<()> {
+        if self.fast_open {
+            sys::set_fast_open_connect(socket)?;
+        }
+        self.configure(socket, addr)?;
+        if let Some(local_addr) = self.local_addr {
+            socket.bind(&local_addr.into())?;
+        }
+        Ok(())
+    }
+
+    fn configure_bind(&self, socket: &Socket, addr: SocketAddr) -> io::Result<()> {
+        if self.reuse_address.is_none() {
+            socket.set_reuse_address(true)?;
+        }
+        if self.only_v6.is_none() && addr.ip() == Ipv6Addr::UNSPECIFIED {
+            socket.set_only_v6(false)?;
+        }
+        if self.fast_open {
+            sys::set_fast_open(socket, self.backlog)?;
+        }
+        self.configure(socket, addr)
+    }
+
+    pub(crate) fn connect_mptcp(&self, addr: SocketAddr, nonblocking: bool) -> io::Result<Socket> {
+        let mut builder = sys::MptcpSocketBuilder::new_for_addr(addr)?;
+        self.configure_connect(builder.socket(), addr)?;
+        if nonblocking {
+            builder = builder.set_nonblocking()?;
+        }
+        builder.connect(addr)
+    }
+
+    pub(crate) fn connect_tcp(&self, addr: SocketAddr, nonblocking: bool) -> io::Result<Socket> {
+        let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+        self.configure_connect(&socket, addr)?;
+        socket.set_nonblocking(nonblocking)?;
+
+        match socket
+            .connect(&addr.into())
+            .map_err(|e| (e.raw_os_error(), e))
+        {
+            Err((Some(errno), err)) if errno != libc::EINPROGRESS => Err(err),
+            _ => Ok(socket),
+        }
+    }
+
+    pub(crate) fn bind_mptcp(&self, addr: SocketAddr, nonblocking: bool) -> io::Result<Socket> {
+        let mut builder = sys::MptcpSocketBuilder::new_for_addr(addr)?;
+        self.configure_bind(builder.socket(), addr)?;
+        if nonblocking {
+            builder = builder.set_nonblocking()?;
+        }
+        builder.bind(addr, self.backlog)
+    }
+
+    pub(crate) fn bind_tcp(&self, addr: SocketAddr, nonblocking: bool) -> io::Result<Socket> {
+        let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+        self.configure_bind(&socket, addr)?;
+        socket.set_nonblocking(nonblocking)?;
+        socket.bind(&addr.into())?;
+        socket.listen(self.backlog)?;
+        Ok(socket)
+    }
+
+    pub(crate) fn bind_shards<F>(
+        &self,
+        addr: SocketAddr,
+        shards: usize,
+        bind: F,
+    ) -> io::Result<Vec<Socket>>
+    where
+        F: Fn(&Self, SocketAddr) -> io::Result<Socket>,
+    {
+        if shards == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "cannot create 0 shards",
+            ));
+        }
+
+        let builder = self.clone().reuse_port(true);
+        let first = bind(&builder, addr)?;
+        // Use the bound address in case an ephemeral port was requested
+        let addr = first.local_addr()?.as_socket().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "not an inet socket address")
+        })?;
+
+        let mut sockets = vec![first];
+        for _ in 1..shards {
+            sockets.push(bind(&builder, addr)?);
+        }
+
+        Ok(sockets)
+    }
+}
+
+/// Options for MPTCP listeners, see `bind_mptcp_with`.
+///
+/// # Example
+///
+/// ```rust
+/// use mptcp::{ListenOptions, MptcpListenerExt};
+/// use std::net::TcpListener;
+///
+/// let listener = TcpListener::bind_mptcp_with(
+///     "127.0.0.1:0",
+///     ListenOptions {
+///         backlog: 4096,
+///         reuse_port: true,
+///         ..Default::default()
+///     },
+/// )
+/// .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct ListenOptions {
+    /// The maximum length of the queue of pending connections. Defaults to 128.
+    pub backlog: i32,
+    /// Whether to set `SO_REUSEADDR`. Defaults to `true`.
+    pub reuse_address: bool,
+    /// Whether to set `SO_REUSEPORT`. Defaults to `false`.
+    pub reuse_port: bool,
+    /// The value of `IPV6_V6ONLY` for IPv6 addresses. Defaults to `false` for the
+    /// unspecified IPv6 address (`[::]`), so that the listener is dual-stack, and to
+    /// the system setting otherwise.
+    pub v6_only: Option<bool>,
+    /// The network interface to bind to using `SO_BINDTODEVICE`. Only supported on Linux.
+    pub device: Option<String>,
+    /// Whether to enable TCP Fast Open, see `MptcpSocketBuilder::fast_open`.
+    /// Defaults to `false`.
+    pub fast_open: bool,
+}
+
+impl Default for ListenOptions {
+    fn default() -> Self {
+        Self {
+            backlog: 128,
+            reuse_address: true,
+            reuse_port: false,
+            v6_only: None,
+            device: None,
+            fast_open: false,
+        }
+    }
+}
+
+impl From<ListenOptions> for MptcpSocketBuilder {
+    fn from(options: ListenOptions) -> Self {
+        let mut builder = Self::new()
+            .backlog(options.backlog)
+            .reuse_address(options.reuse_address)
+            .reuse_port(options.reuse_port)
+            .fast_open(options.fast_open);
+        if let Some(only_v6) = options.v6_only {
+            builder = builder.only_v6(only_v6);
+        }
+        if let Some(device) = &options.device {
+            builder = builder.bind_device(device);
+        }
+        builder
+    }
+}
+
+/// Options for MPTCP connections, see `connect_mptcp_with`.
+///
+/// # Example
+///
+/// ```rust
+/// use mptcp::{ConnectOptions, MptcpStreamExt};
+/// use std::net::{TcpListener, TcpStream};
+///
+/// let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+/// let stream = TcpStream::connect_mptcp_with(
+///     listener.local_addr().unwrap(),
+///     ConnectOptions {
+///         fast_open: true,
+///         ..Default::default()
+///     },
+/// )
+/// .unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ConnectOptions {
+    /// The local address to bind to before connecting. Defaults to `None`.
+    pub local_addr: Option<SocketAddr>,
+    /// The network interface to bind to using `SO_BINDTODEVICE`. Only supported on Linux.
+    pub device: Option<String>,
+    /// Whether to enable TCP Fast Open, see `MptcpSocketBuilder::fast_open`.
+    /// Defaults to `false`.
+    pub fast_open: bool,
+}
+
+impl From<ConnectOptions> for MptcpSocketBuilder {
+    fn from(options: ConnectOptions) -> Self {
+        let mut builder = Self::new().fast_open(options.fast_open);
+        if let Some(addr) = options.local_addr {
+            builder = builder.local_addr(addr);
+        }
+        if let Some(device) = &options.device {
+            builder = builder.bind_device(device);
+        }
+        builder
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn bind_device(socket: &Socket, interface: &str) -> io::Result<()> {
+    socket.bind_device(Some(interface.as_bytes()))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn bind_device(_socket: &Socket, _interface: &str) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
@@ -0,0 +1,169 @@
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{MptcpExt, MptcpStatus};
+
+/// A bounded cache of destinations for which MPTCP connections fell back to TCP.
+///
+/// When the path to a destination strips MPTCP options, every new connection pays
+/// for an MPTCP attempt before falling back to TCP. The `connect_mptcp_cached`
+/// methods of the stream extension traits use this cache to connect to such
+/// destinations with plain TCP directly, until the entry expires.
+///
+/// The cache is safe to share between threads, e.g. behind an `Arc` or in a `static`.
+#[derive(Debug)]
+pub struct FallbackCache {
+    entries: Mutex<HashMap<SocketAddr, Instant>>,
+    capacity: usize,
+    ttl: Duration,
+}
+
+impl FallbackCache {
+    /// Creates a cache holding at most `capacity` destinations, each remembered
+    /// for `ttl`.
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            capacity,
+            ttl,
+        }
+    }
+
+    /// Returns whether connections to `addr` are known to fall back to TCP.
+    pub fn contains(&self, addr: &SocketAddr) -> bool {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(addr) {
+            Some(expiry) if *expiry > Instant::now() => true,
+            Some(_) => {
+                entries.remove(addr);
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// Remembers that connections to `addr` fall back to TCP.
+    ///
+    /// If the cache is full, expired entries are evicted first, then the entry
+    /// closest to expiry.
+    pub fn insert(&self, addr: SocketAddr) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+
+        if entries.len() >= self.capacity && !entries.contains_key(&addr) {
+            entries.retain(|_, expiry| *expiry > now);
+        }
+
+        if entries.len() >= self.capacity && !entries.contains_key(&addr) {
+            if let Some(oldest) = entries
+                .iter()
+                .min_by_key(|(_, expiry)| **expiry)
+                .map(|(addr, _)| *addr)
+            {
+                entries.remove(&oldest);
+            }
+        }
+
+        entries.insert(addr, now + self.ttl);
+    }
+
+    /// Records the outcome of a connection to `addr`.
+    ///
+    /// The destination is remembered if the MPTCP status of `socket` reports a
+    /// fallback to TCP. This can be called when the connection ends, to also catch
+    /// fallbacks happening after the connection was established.
+    pub fn record<S: MptcpExt>(&self, addr: SocketAddr, socket: &S) {
+        if matches!(
+            socket.mptcp_status(),
+            MptcpStatus::Mptcp { has_fallback: true }
+        ) {
+            self.insert(addr);
+        }
+    }
+
+    /// Forgets `addr`, so that the next connection attempts MPTCP again.
+    pub fn remove(&self, addr: &SocketAddr) {
+        self.entries.lock().unwrap().remove(addr);
+    }
+
+    /// Forgets all destinations.
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    /// Returns the number of destinations in the cache, including expired ones
+    /// that were not evicted yet.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// Returns whether the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread::sleep;
+
+    use super::*;
+
+    #[test]
+    fn test_insert_contains() {
+        let cache = FallbackCache::new(2, Duration::from_secs(60));
+        let addr = "127.0.0.1:80".parse().unwrap();
+
+        assert!(!cache.contains(&addr));
+        cache.insert(addr);
+        assert!(cache.contains(&addr));
+        cache.remove(&addr);
+        assert!(!cache.contains(&addr));
+    }
+
+    #[test]
+    fn test_expiry() {
+        let cache = FallbackCache::new(2, Duration::from_millis(10));
+        let addr = "127.0.0.1:80".parse().unwrap();
+
+        cache.insert(addr);
+        sleep(Duration::from_millis(20));
+        assert!(!cache.contains(&addr));
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_capacity() {
+        let cache = FallbackCache::new(2, Duration::from_secs(60));
+        let addrs: Vec<SocketAddr> = ["127.0.0.1:80", "127.0.0.1:81", "127.0.0.1:82"]
+            .iter()
+            .map(|addr| addr.parse().unwrap())
+            .collect();
+
+        for addr in &addrs {
+            cache.insert(*addr);
+        }
+
+        assert_eq!(cache.len(), 2);
+        assert!(!cache.contains(&addrs[0]));
+        assert!(cache.contains(&addrs[1]));
+        assert!(cache.contains(&addrs[2]));
+    }
+
+    #[test]
+    fn test_zero_capacity() {
+        let cache = FallbackCache::new(0, Duration::from_secs(60));
+        let addr = "127.0.0.1:80".parse().unwrap();
+
+        cache.insert(addr);
+        assert!(!cache.contains(&addr));
+    }
+}
@@ -0,0 +1,185 @@
+use std::net::SocketAddr;
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+use std::{future::Future, io, time::Duration};
+
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+use crate::{MptcpExt, MptcpStatus};
+
+/// The type of an event sent by the in-kernel MPTCP path manager.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum MptcpEventKind {
+    /// A new MPTCP connection was created.
+    Created,
+    /// An MPTCP connection was established.
+    Established,
+    /// An MPTCP connection was closed.
+    Closed,
+    /// The peer announced a new address.
+    Announced,
+    /// The peer removed an address.
+    Removed,
+    /// A new subflow was established.
+    SubflowEstablished,
+    /// A subflow was closed.
+    SubflowClosed,
+    /// The backup priority of a subflow changed.
+    SubflowPriority,
+    /// A new MPTCP listener was created.
+    ListenerCreated,
+    /// An MPTCP listener was closed.
+    ListenerClosed,
+}
+
+/// An event sent by the in-kernel MPTCP path manager.
+///
+/// Only the attributes relevant to the kind of event are set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MptcpEvent {
+    /// The type of the event.
+    pub kind: MptcpEventKind,
+    /// The local token of the MPTCP connection, as in `MptcpInfo::token`.
+    pub token: Option<u32>,
+    /// The local address identifier.
+    pub local_id: Option<u8>,
+    /// The remote address identifier.
+    pub remote_id: Option<u8>,
+    /// The local address of the subflow or listener.
+    pub local_addr: Option<SocketAddr>,
+    /// The remote address of the subflow, or the announced address.
+    pub remote_addr: Option<SocketAddr>,
+    /// Whether the subflow is a backup subflow.
+    pub backup: Option<bool>,
+    /// The error of a closed subflow, as an errno value.
+    pub error: Option<u8>,
+}
+
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+/// The effect of a batch of path manager events on an MPTCP connection, ordered by
+/// significance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum PmUpdate {
+    /// None of the events is about the connection.
+    Unrelated,
+    /// The connection changed, e.g. a subflow was established or an address announced.
+    Changed,
+    /// A subflow of the connection was closed, the connection itself is still open.
+    SubflowClosed,
+    /// The connection was closed.
+    Closed,
+}
+
+#[cfg(all(target_os = "linux", any(feature = "tokio", feature = "async-std")))]
+impl PmUpdate {
+    /// Classifies `events` for the connection with the given token, keeping the most
+    /// significant update.
+    pub(crate) fn from_events(events: &[MptcpEvent], token: u32) -> Self {
+        events
+            .iter()
+            .filter(|event| event.token == Some(token))
+            .map(|event| match event.kind {
+                MptcpEventKind::Closed => Self::Closed,
+                MptcpEventKind::SubflowClosed => Self::SubflowClosed,
+                _ => Self::Changed,
+            })
+            .max()
+            .unwrap_or(Self::Unrelated)
+    }
+}
+
+/// The interval at which `MPTCP_INFO` is polled when no path manager event is
+/// received.
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Waits until the connection of `stream` falls back to TCP, see the
+/// `wait_for_fallback` methods of the async `MptcpStream`s.
+///
+/// `wait` is called with the token of the connection, and waits for at most
+/// `POLL_INTERVAL` for a path manager event about it.
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+pub(crate) async fn wait_for_fallback<S, F, Fut>(
+    stream: &S,
+    status: MptcpStatus,
+    mut wait: F,
+) -> io::Result<()>
+where
+    S: MptcpExt,
+    F: FnMut(u32) -> Fut,
+    Fut: Future<Output = PmUpdate>,
+{
+    if status == MptcpStatus::Tcp {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "the stream does not use MPTCP",
+        ));
+    }
+
+    loop {
+        let info = match stream.mptcp_info() {
+            Ok(info) if !info.has_fallback() => info,
+            Ok(_) => return Ok(()),
+            // MPTCP_INFO is not available anymore after a fallback
+            Err(_) if stream.mptcp_status() == (MptcpStatus::Mptcp { has_fallback: true }) => {
+                return Ok(())
+            }
+            Err(err) => return Err(err),
+        };
+
+        // Closing a subflow does not close the connection: only check the flags again
+        if wait(info.token).await == PmUpdate::Closed
+            && stream.mptcp_info().is_ok_and(|info| !info.has_fallback())
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                "the connection was closed without falling back",
+            ));
+        }
+    }
+}
+
+#[cfg(all(
+    test,
+    target_os = "linux",
+    any(feature = "tokio", feature = "async-std")
+))]
+mod tests {
+    use super::*;
+
+    fn event(kind: MptcpEventKind, token: u32) -> MptcpEvent {
+        MptcpEvent {
+            kind,
+            token: Some(token),
+            local_id: None,
+            remote_id: None,
+            local_addr: None,
+            remote_addr: None,
+            backup: None,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_pm_update() {
+        assert_eq!(PmUpdate::from_events(&[], 1), PmUpdate::Unrelated);
+
+        let events = [
+            event(MptcpEventKind::Closed, 2),
+            event(MptcpEventKind::SubflowEstablished, 1),
+        ];
+        assert_eq!(PmUpdate::from_events(&events, 1), PmUpdate::Changed);
+        assert_eq!(PmUpdate::from_events(&events, 2), PmUpdate::Closed);
+        assert_eq!(PmUpdate::from_events(&events, 3), PmUpdate::Unrelated);
+
+        let events = [
+            event(MptcpEventKind::SubflowClosed, 1),
+            event(MptcpEventKind::Announced, 1),
+        ];
+        assert_eq!(PmUpdate::from_events(&events, 1), PmUpdate::SubflowClosed);
+
+        let events = [
+            event(MptcpEventKind::SubflowClosed, 1),
+            event(MptcpEventKind::Closed, 1),
+        ];
+        assert_eq!(PmUpdate::from_events(&events, 1), PmUpdate::Closed);
+    }
+}
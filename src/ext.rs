@@ -1,6 +1,13 @@
-use std::os::fd::AsRawFd;
+use std::{
+    io,
+    os::fd::{AsRawFd, BorrowedFd, OwnedFd},
+};
 
-use crate::sys::MptcpSocketRef;
+use socket2::Socket;
+
+#[cfg(feature = "tokio")]
+use crate::MptcpStats;
+use crate::{sys::MptcpSocketRef, MptcpInfo, MptcpSubflow};
 
 /// Represents whether to fallback to TCP in case MPTCP isn't available.
 pub enum MptcpOpt {
@@ -10,12 +17,31 @@ pub enum MptcpOpt {
     NoFallback,
 }
 
-#[derive(Debug)]
+/// The protocol used by a socket.
+///
+/// With the `serde` feature, it is serialized as `{"protocol": "tcp"}` or
+/// `{"protocol": "mptcp", "has_fallback": false}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "protocol", rename_all = "snake_case"))]
 pub enum MptcpStatus {
     Tcp,
     Mptcp { has_fallback: bool },
 }
 
+impl MptcpStatus {
+    /// Returns whether the socket uses MPTCP, i.e. it is an MPTCP socket which did not
+    /// fall back to TCP.
+    pub fn is_mptcp(&self) -> bool {
+        matches!(
+            self,
+            MptcpStatus::Mptcp {
+                has_fallback: false
+            }
+        )
+    }
+}
+
 /// A trait for extending the functionality of types that implement `AsRawFd`.
 pub trait MptcpExt: AsRawFd + Sized {
     /// Returns the MPTCP status of the socket.
@@ -51,11 +77,110 @@ pub trait MptcpExt: AsRawFd + Sized {
         }
         MptcpStatus::Tcp
     }
+
+    /// Returns information about the MPTCP connection of the socket.
+    ///
+    /// Only supported on Linux >= 5.16. Returns an error if the socket does not use
+    /// MPTCP or fell back to TCP.
+    fn mptcp_info(&self) -> io::Result<MptcpInfo> {
+        let sock: MptcpSocketRef<'_, _> = self.into();
+        sock.info()
+    }
+
+    /// Returns the addresses of the subflows of the MPTCP connection of the socket.
+    ///
+    /// Only supported on Linux >= 5.17. Returns an error if the socket does not use
+    /// MPTCP or fell back to TCP.
+    fn mptcp_subflows(&self) -> io::Result<Vec<MptcpSubflow>> {
+        let sock: MptcpSocketRef<'_, _> = self.into();
+        sock.subflows()
+    }
+
+    /// Watches the `MptcpInfo` of the connection, taking a snapshot every `interval`.
+    ///
+    /// The snapshots include the throughput, retransmission rate and subflow count
+    /// changes since the previous one. They are taken by a task spawned on the current
+    /// Tokio runtime, which stops when all receivers are dropped, the socket is closed,
+    /// or `MPTCP_INFO` cannot be retrieved anymore, e.g. after a fallback to TCP: the
+    /// receivers are then notified that the sender was dropped.
+    ///
+    /// The task does not keep the socket open, the caller must keep it alive for as
+    /// long as snapshots are needed. Once it is closed, the task stops at the next
+    /// snapshot, even if its file descriptor was reused by another socket.
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - The interval between snapshots.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing a receiver of the snapshots, or an `io::Error`
+    /// if the socket does not use MPTCP, `interval` is zero or this is not called from
+    /// a Tokio runtime.
+    #[cfg(feature = "tokio")]
+    fn watch_stats(
+        &self,
+        interval: std::time::Duration,
+    ) -> io::Result<tokio::sync::watch::Receiver<MptcpStats>> {
+        crate::stats::watch::watch_stats(self.as_raw_fd(), interval)
+    }
+
+    /// Records the subflows of the connection in the `mptcp_subflows` and
+    /// `mptcp_subflow_bytes` histograms of the `metrics` crate.
+    ///
+    /// `mptcp_subflows` receives the number of established subflows, including the
+    /// initial one, and `mptcp_subflow_bytes` the bytes sent and received by each
+    /// established subflow, labeled with its `local_addr` and `remote_addr`. This is
+    /// typically called before closing the connection.
+    ///
+    /// Only supported on Linux >= 5.17.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing the recorded `MptcpInfo`, or an `io::Error`
+    /// if the socket does not use MPTCP or fell back to TCP.
+    #[cfg(feature = "metrics")]
+    fn record_mptcp_metrics(&self) -> io::Result<MptcpInfo> {
+        let info = self.mptcp_info()?;
+        let sock: MptcpSocketRef<'_, _> = self.into();
+        // Both options list the subflows in the same order
+        let subflows: Vec<_> = sock
+            .subflows()?
+            .into_iter()
+            .zip(sock.subflow_bytes()?)
+            .collect();
+        crate::telemetry::subflows(&info, &subflows);
+        Ok(info)
+    }
+
+    /// Sets the attributes returned by `mptcp_attributes` on the active OpenTelemetry
+    /// span.
+    ///
+    /// The active span is the one of the current OpenTelemetry `Context`. With
+    /// `tracing-opentelemetry`, use `OpenTelemetrySpanExt::set_attribute` with the
+    /// attributes returned by `mptcp_attributes` instead.
+    #[cfg(feature = "opentelemetry")]
+    fn set_mptcp_span_attributes(&self) {
+        let attributes =
+            crate::mptcp_attributes(self.mptcp_status(), self.mptcp_info().ok().as_ref());
+        opentelemetry::trace::get_active_span(|span| span.set_attributes(attributes));
+    }
 }
 
+impl MptcpExt for Socket {}
+
+impl MptcpExt for OwnedFd {}
+
+impl MptcpExt for BorrowedFd<'_> {}
+
 #[cfg(all(test, target_os = "linux"))]
 mod tests {
-    use std::net::{TcpListener, TcpStream};
+    use std::{
+        net::{TcpListener, TcpStream},
+        os::fd::{AsFd, OwnedFd},
+    };
+
+    use socket2::Socket;
 
     use crate::sys::{has_mptcp_info, is_mptcp_enabled};
     use crate::{MptcpExt, MptcpListenerExt, MptcpSocket, MptcpStatus, MptcpStreamExt};
@@ -124,4 +249,63 @@ mod tests {
 
         assert!(matches!(stream.mptcp_status(), MptcpStatus::Tcp));
     }
+
+    #[test]
+    fn test_mptcp_fd() {
+        let mptcp_enabled = is_mptcp_enabled();
+
+        let listener = TcpListener::bind_mptcp("127.0.0.1:0").unwrap();
+        let is_mptcp = matches!(listener, MptcpSocket::Mptcp(..));
+        assert_eq!(is_mptcp, mptcp_enabled);
+
+        let listener = listener.into_socket();
+        assert_eq!(
+            matches!(listener.as_fd().mptcp_status(), MptcpStatus::Mptcp { .. }),
+            mptcp_enabled
+        );
+
+        let socket = Socket::from(listener);
+        assert_eq!(
+            matches!(socket.mptcp_status(), MptcpStatus::Mptcp { .. }),
+            mptcp_enabled
+        );
+
+        let fd = OwnedFd::from(socket);
+        assert_eq!(
+            matches!(fd.mptcp_status(), MptcpStatus::Mptcp { .. }),
+            mptcp_enabled
+        );
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        assert!(matches!(
+            OwnedFd::from(listener).mptcp_status(),
+            MptcpStatus::Tcp
+        ));
+    }
+
+    #[test]
+    fn test_mptcp_info_subflows() {
+        if !is_mptcp_enabled() || !has_mptcp_info() {
+            return;
+        }
+
+        let listener = TcpListener::bind_mptcp("127.0.0.1:0").unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        let stream = TcpStream::connect_mptcp(local_addr).unwrap();
+        let (accepted, _) = listener.accept().unwrap();
+
+        let info = stream.mptcp_info().unwrap();
+        assert_ne!(info.token, 0);
+        assert!(accepted.mptcp_info().is_ok());
+
+        let subflows = stream.mptcp_subflows().unwrap();
+        assert_eq!(subflows.len(), 1);
+        assert_eq!(subflows[0].local_addr, stream.local_addr().unwrap());
+        assert_eq!(subflows[0].remote_addr, local_addr);
+
+        let tcp = TcpStream::connect(local_addr).unwrap();
+        assert!(tcp.mptcp_info().is_err());
+        assert!(tcp.mptcp_subflows().is_err());
+    }
 }
@@ -0,0 +1,93 @@
+use std::{
+    fmt, io,
+    net::SocketAddr,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::{sys, MptcpExt, MptcpSocket};
+
+type RejectHandler<S> = dyn Fn(S, SocketAddr) + Send + Sync;
+
+/// A policy rejecting accepted connections that do not use MPTCP.
+///
+/// Used by the `accept_mptcp_filtered` methods of the listener extension traits.
+/// Connections whose peer did not negotiate MPTCP, or which fell back to TCP, are
+/// closed, or passed to the handler set with `with_handler`. Filtering requires
+/// Linux >= 5.16, where fallbacks can be detected: the accept methods return
+/// `io::ErrorKind::Unsupported` on older kernels.
+///
+/// The filter is safe to share between threads, e.g. between the accept loops of
+/// sharded listeners.
+pub struct AcceptFilter<S> {
+    rejected: AtomicU64,
+    handler: Option<Box<RejectHandler<S>>>,
+}
+
+impl<S: MptcpExt> AcceptFilter<S> {
+    /// Creates a filter closing the rejected connections.
+    pub fn new() -> Self {
+        Self {
+            rejected: AtomicU64::new(0),
+            handler: None,
+        }
+    }
+
+    /// Creates a filter passing the rejected connections and the address of their
+    /// peer to `handler`, e.g. to serve them with a degraded service.
+    ///
+    /// The handler is called from the accept method, it should not block: async
+    /// handlers should spawn a task to handle the connection.
+    pub fn with_handler<F>(handler: F) -> Self
+    where
+        F: Fn(S, SocketAddr) + Send + Sync + 'static,
+    {
+        Self {
+            rejected: AtomicU64::new(0),
+            handler: Some(Box::new(handler)),
+        }
+    }
+
+    /// Returns the number of connections rejected by this filter.
+    pub fn rejected(&self) -> u64 {
+        self.rejected.load(Ordering::Relaxed)
+    }
+
+    /// Returns an error if the kernel cannot tell whether a connection fell back to
+    /// TCP, in which case the filter would let fallbacks through.
+    pub(crate) fn check_supported(&self) -> io::Result<()> {
+        if sys::has_mptcp_info() {
+            Ok(())
+        } else {
+            Err(io::ErrorKind::Unsupported.into())
+        }
+    }
+
+    /// Returns the connection if it uses MPTCP, otherwise rejects it.
+    pub(crate) fn filter(&self, sock: S, addr: SocketAddr) -> Option<S> {
+        match MptcpSocket::from_accepted(sock) {
+            MptcpSocket::Mptcp(sock) => Some(sock),
+            MptcpSocket::Tcp(sock) => {
+                self.rejected.fetch_add(1, Ordering::Relaxed);
+                if let Some(handler) = &self.handler {
+                    handler(sock, addr);
+                }
+                None
+            }
+        }
+    }
+}
+
+impl<S: MptcpExt> Default for AcceptFilter<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> fmt::Debug for AcceptFilter<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AcceptFilter")
+            .field("rejected", &self.rejected)
+            .field("handler", &self.handler.is_some())
+            .finish()
+    }
+}
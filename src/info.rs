@@ -0,0 +1,125 @@
+use std::net::SocketAddr;
+
+/// Information about an MPTCP connection, as reported by `MPTCP_INFO`.
+///
+/// Fields not supported by the running kernel are left to zero. See the
+/// `struct mptcp_info` documentation of the kernel for their exact meaning.
+///
+/// With the `serde` feature, it is serialized as a map with the names of the
+/// fields as keys.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MptcpInfo {
+    /// The number of additional subflows currently established.
+    pub subflows: u8,
+    /// The number of addresses announced to the peer.
+    pub add_addr_signal: u8,
+    /// The number of addresses announced by the peer and accepted.
+    pub add_addr_accepted: u8,
+    /// The maximum number of additional subflows.
+    pub subflows_max: u8,
+    /// The maximum number of addresses announced to the peer.
+    pub add_addr_signal_max: u8,
+    /// The maximum number of addresses announced by the peer that can be accepted.
+    pub add_addr_accepted_max: u8,
+    /// The `MPTCP_INFO_FLAG_*` flags of the connection.
+    pub flags: u32,
+    /// The local token of the connection.
+    pub token: u32,
+    /// The next data sequence number to send.
+    pub write_seq: u64,
+    /// The first unacknowledged data sequence number.
+    pub snd_una: u64,
+    /// The next data sequence number expected from the peer.
+    pub rcv_nxt: u64,
+    /// The number of local addresses used by the subflows.
+    pub local_addr_used: u8,
+    /// The maximum number of local addresses that can be used.
+    pub local_addr_max: u8,
+    /// Whether the DSS checksum is enabled.
+    pub csum_enabled: bool,
+    /// The number of MPTCP-level retransmission intervals.
+    pub retransmits: u32,
+    /// The number of bytes retransmitted at the MPTCP level.
+    pub bytes_retrans: u64,
+    /// The number of bytes sent.
+    pub bytes_sent: u64,
+    /// The number of bytes received.
+    pub bytes_received: u64,
+    /// The number of bytes acknowledged by the peer.
+    pub bytes_acked: u64,
+    /// The total number of subflows created, including the initial one and the
+    /// closed ones.
+    pub subflows_total: u8,
+    /// The time since the last data was sent, in milliseconds.
+    pub last_data_sent: u32,
+    /// The time since the last data was received, in milliseconds.
+    pub last_data_recv: u32,
+    /// The time since the last data acknowledgment was received, in milliseconds.
+    pub last_ack_recv: u32,
+}
+
+impl MptcpInfo {
+    /// The connection fell back to TCP.
+    pub const FLAG_FALLBACK: u32 = 1 << 0;
+    /// The key of the peer was received.
+    pub const FLAG_REMOTE_KEY_RECEIVED: u32 = 1 << 1;
+
+    /// Returns whether the `FLAG_FALLBACK` flag is set.
+    pub fn has_fallback(&self) -> bool {
+        self.flags & Self::FLAG_FALLBACK != 0
+    }
+}
+
+/// The addresses of a subflow of an MPTCP connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MptcpSubflow {
+    /// The local address of the subflow.
+    pub local_addr: SocketAddr,
+    /// The remote address of the subflow.
+    pub remote_addr: SocketAddr,
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+
+    use crate::MptcpStatus;
+
+    #[test]
+    fn test_serde() {
+        let status = MptcpStatus::Mptcp {
+            has_fallback: false,
+        };
+        let json = serde_json::to_string(&status).unwrap();
+        assert_eq!(json, r#"{"protocol":"mptcp","has_fallback":false}"#);
+        assert_eq!(serde_json::from_str::<MptcpStatus>(&json).unwrap(), status);
+        let json = serde_json::to_string(&MptcpStatus::Tcp).unwrap();
+        assert_eq!(json, r#"{"protocol":"tcp"}"#);
+
+        let subflow = MptcpSubflow {
+            local_addr: "127.0.0.1:1234".parse().unwrap(),
+            remote_addr: "[::1]:80".parse().unwrap(),
+        };
+        let json = serde_json::to_string(&subflow).unwrap();
+        assert_eq!(
+            json,
+            r#"{"local_addr":"127.0.0.1:1234","remote_addr":"[::1]:80"}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<MptcpSubflow>(&json).unwrap(),
+            subflow
+        );
+
+        let info = MptcpInfo {
+            subflows: 1,
+            token: 42,
+            ..Default::default()
+        };
+        let value = serde_json::to_value(info).unwrap();
+        assert_eq!(value["subflows"], 1);
+        assert_eq!(value["token"], 42);
+        assert_eq!(serde_json::from_value::<MptcpInfo>(value).unwrap(), info);
+    }
+}
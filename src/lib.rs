@@ -4,10 +4,19 @@
 //! It provides the core functionality for working with Multipath TCP (MPTCP) sockets.
 //!
 //! The crate is organized into several submodules:
+//! - `builder`: Contains a builder to configure MPTCP sockets before connecting or binding.
 //! - `socket`: Contains the MPTCP socket implementation.
+//! - `stats`: Contains periodic snapshots of the information about MPTCP connections.
+//! - `event`: Handles the events sent by the in-kernel MPTCP path manager.
+//! - `info`: Contains the information reported about MPTCP connections and their subflows.
+//! - `cache`: Contains a cache of destinations for which MPTCP falls back to TCP.
+//! - `filter`: Contains a policy rejecting accepted connections that do not use MPTCP.
+//! - `otel` (feature: "opentelemetry"): Describes MPTCP connections with OpenTelemetry attributes.
 //! - `std` (feature: "std"): Provides a standard library implementation for MPTCP.
 //! - `tokio` (feature: "tokio"): Provides a Tokio-based implementation for MPTCP.
 //! - `async_std` (feature: "async-std"): Provides an async-std-based implementation for MPTCP.
+//! - `axum` (feature: "axum"): Serves MPTCP listeners with axum.
+//! - `tower` (feature: "tower"): Provides a tower middleware exposing the MPTCP status of connections to requests.
 //!
 //! Example:
 //!
@@ -20,12 +29,30 @@
 //!     TcpStream::connect_mptcp(addr).map(|stream| stream.into())
 //! }
 //! ```
+mod builder;
+mod cache;
+mod event;
 mod ext;
+mod filter;
+mod info;
+#[cfg(feature = "opentelemetry")]
+mod otel;
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+mod race;
 mod socket;
+mod stats;
 mod sys;
+mod telemetry;
 
+pub use builder::*;
+pub use cache::*;
 pub use ext::*;
+pub use filter::*;
+pub use info::*;
+#[cfg(feature = "opentelemetry")]
+pub use otel::*;
 pub use socket::*;
+pub use stats::*;
 
 #[cfg(feature = "std")]
 mod std;
@@ -37,3 +64,9 @@ pub mod tokio;
 
 #[cfg(feature = "async-std")]
 pub mod async_std;
+
+#[cfg(feature = "axum")]
+pub mod axum;
+
+#[cfg(feature = "tower")]
+pub mod tower;
@@ -0,0 +1,150 @@
+use opentelemetry::KeyValue;
+
+use crate::{MptcpInfo, MptcpStatus};
+
+/// The `network.transport` attribute.
+pub const NETWORK_TRANSPORT: &str = "network.transport";
+/// The `mptcp.subflows` attribute: the number of established subflows, including
+/// the initial one.
+pub const MPTCP_SUBFLOWS: &str = "mptcp.subflows";
+/// The `mptcp.status` attribute: the MPTCP status of the socket.
+///
+/// Its value is `mptcp` if the connection uses MPTCP, `fallback` if the MPTCP
+/// connection fell back to TCP, because the peer does not support MPTCP or later
+/// during the connection, and `tcp` if the socket is a TCP socket, because MPTCP was
+/// not requested or is not available.
+pub const MPTCP_STATUS: &str = "mptcp.status";
+
+/// Returns the OpenTelemetry attributes describing a connection.
+///
+/// `network.transport` is `mptcp` or `tcp`, and `mptcp.status` tells TCP sockets
+/// from fallbacks. `mptcp.subflows` is only set if `info` is given.
+///
+/// # Arguments
+///
+/// * `status` - The MPTCP status of the connection.
+/// * `info` - The information about the connection, if available.
+///
+/// # Returns
+///
+/// Returns the attributes, to be set on a span or recorded with a metric.
+pub fn mptcp_attributes(status: MptcpStatus, info: Option<&MptcpInfo>) -> Vec<KeyValue> {
+    let (transport, status) = match status {
+        _ if status.is_mptcp() => ("mptcp", "mptcp"),
+        MptcpStatus::Mptcp { .. } => ("tcp", "fallback"),
+        MptcpStatus::Tcp => ("tcp", "tcp"),
+    };
+    let mut attributes = vec![
+        KeyValue::new(NETWORK_TRANSPORT, transport),
+        KeyValue::new(MPTCP_STATUS, status),
+    ];
+
+    if let Some(info) = info {
+        attributes.push(KeyValue::new(MPTCP_SUBFLOWS, info.subflows as i64 + 1));
+    }
+
+    attributes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mptcp_attributes() {
+        let info = MptcpInfo {
+            subflows: 1,
+            ..Default::default()
+        };
+        let attributes = mptcp_attributes(
+            MptcpStatus::Mptcp {
+                has_fallback: false,
+            },
+            Some(&info),
+        );
+        assert_eq!(
+            attributes,
+            vec![
+                KeyValue::new(NETWORK_TRANSPORT, "mptcp"),
+                KeyValue::new(MPTCP_STATUS, "mptcp"),
+                KeyValue::new(MPTCP_SUBFLOWS, 2),
+            ]
+        );
+
+        let attributes = mptcp_attributes(MptcpStatus::Mptcp { has_fallback: true }, None);
+        assert_eq!(
+            attributes,
+            vec![
+                KeyValue::new(NETWORK_TRANSPORT, "tcp"),
+                KeyValue::new(MPTCP_STATUS, "fallback"),
+            ]
+        );
+
+        let attributes = mptcp_attributes(MptcpStatus::Tcp, None);
+        assert_eq!(attributes[1], KeyValue::new(MPTCP_STATUS, "tcp"));
+    }
+
+    #[cfg(all(target_os = "linux", feature = "std"))]
+    #[test]
+    fn test_set_mptcp_span_attributes() {
+        use std::{
+            borrow::Cow,
+            net::{TcpListener, TcpStream},
+            sync::{Arc, Mutex},
+            time::SystemTime,
+        };
+
+        use opentelemetry::{
+            trace::{Span, SpanContext, Status, TraceContextExt},
+            Context,
+        };
+
+        use crate::{sys::is_mptcp_enabled, MptcpExt, MptcpListenerExt, MptcpStreamExt};
+
+        struct TestSpan(Arc<Mutex<Vec<KeyValue>>>);
+
+        impl Span for TestSpan {
+            fn add_event_with_timestamp<T>(&mut self, _: T, _: SystemTime, _: Vec<KeyValue>)
+            where
+                T: Into<Cow<'static, str>>,
+            {
+            }
+
+            fn span_context(&self) -> &SpanContext {
+                &SpanContext::NONE
+            }
+
+            fn is_recording(&self) -> bool {
+                true
+            }
+
+            fn set_attribute(&mut self, attribute: KeyValue) {
+                self.0.lock().unwrap().push(attribute);
+            }
+
+            fn set_status(&mut self, _: Status) {}
+
+            fn update_name<T>(&mut self, _: T)
+            where
+                T: Into<Cow<'static, str>>,
+            {
+            }
+
+            fn add_link(&mut self, _: SpanContext, _: Vec<KeyValue>) {}
+
+            fn end_with_timestamp(&mut self, _: SystemTime) {}
+        }
+
+        let listener = TcpListener::bind_mptcp("127.0.0.1:0").unwrap();
+        let stream = TcpStream::connect_mptcp(listener.local_addr().unwrap()).unwrap();
+
+        let attributes = Arc::new(Mutex::new(Vec::new()));
+        let span = TestSpan(attributes.clone());
+        let _guard = Context::current_with_span(span).attach();
+        stream.set_mptcp_span_attributes();
+
+        let attributes = attributes.lock().unwrap();
+        let transport = if is_mptcp_enabled() { "mptcp" } else { "tcp" };
+        assert_eq!(attributes[0], KeyValue::new(NETWORK_TRANSPORT, transport));
+    }
+}
@@ -0,0 +1,69 @@
+use std::{
+    future::{poll_fn, Future},
+    io,
+    pin::pin,
+    task::Poll,
+};
+
+use crate::{
+    telemetry::{self, Fallback},
+    MptcpSocket,
+};
+
+/// Races an MPTCP connection attempt against a TCP one.
+///
+/// The `tcp` attempt is only started once `delay` completes, or as soon as the
+/// `mptcp` attempt fails. The first attempt to succeed is returned, the other one
+/// is dropped, which closes its socket. If both attempts fail, the error of the
+/// TCP attempt is returned.
+pub(crate) async fn race<S>(
+    mptcp: impl Future<Output = io::Result<S>>,
+    delay: impl Future<Output = ()>,
+    tcp: impl Future<Output = io::Result<S>>,
+) -> io::Result<MptcpSocket<S>> {
+    let mut mptcp = pin!(mptcp);
+    let mut delay = pin!(delay);
+    let mut tcp = pin!(tcp);
+
+    let mut mptcp_err = None;
+    let mut tcp_err = None;
+    let mut tcp_started = false;
+
+    poll_fn(|cx| {
+        if mptcp_err.is_none() {
+            match mptcp.as_mut().poll(cx) {
+                Poll::Ready(Ok(sock)) => return Poll::Ready(Ok(MptcpSocket::Mptcp(sock))),
+                Poll::Ready(Err(err)) => mptcp_err = Some(err),
+                Poll::Pending => {}
+            }
+        }
+
+        if !tcp_started {
+            tcp_started = mptcp_err.is_some() || delay.as_mut().poll(cx).is_ready();
+        }
+
+        if tcp_started && tcp_err.is_none() {
+            match tcp.as_mut().poll(cx) {
+                Poll::Ready(Ok(sock)) => {
+                    let reason = match &mptcp_err {
+                        Some(err) => Fallback::Error(err),
+                        None => Fallback::Race,
+                    };
+                    telemetry::fallback("connect", reason);
+                    return Poll::Ready(Ok(MptcpSocket::Tcp(sock)));
+                }
+                Poll::Ready(Err(err)) => tcp_err = Some(err),
+                Poll::Pending => {}
+            }
+        }
+
+        match (&mptcp_err, tcp_err.take()) {
+            (Some(_), Some(err)) => Poll::Ready(Err(err)),
+            (_, err) => {
+                tcp_err = err;
+                Poll::Pending
+            }
+        }
+    })
+    .await
+}
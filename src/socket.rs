@@ -1,5 +1,7 @@
 use std::ops::{Deref, DerefMut};
 
+use crate::{telemetry, MptcpExt};
+
 /// Represents a Multipath TCP (MPTCP) socket.
 ///
 /// The `MptcpSocket` struct is a generic type that can hold either an MPTCP
@@ -22,6 +24,20 @@ impl<T> MptcpSocket<T> {
     }
 }
 
+impl<T: MptcpExt> MptcpSocket<T> {
+    /// Wraps an accepted socket, using the `Mptcp` variant only if the peer
+    /// negotiated MPTCP.
+    pub(crate) fn from_accepted(sock: T) -> Self {
+        let status = sock.mptcp_status();
+        telemetry::accepted(status);
+        if status.is_mptcp() {
+            Self::Mptcp(sock)
+        } else {
+            Self::Tcp(sock)
+        }
+    }
+}
+
 impl<T> Deref for MptcpSocket<T> {
     type Target = T;
 
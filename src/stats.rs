@@ -0,0 +1,200 @@
+use std::time::Duration;
+
+use crate::MptcpInfo;
+
+/// A snapshot of the `MptcpInfo` of a connection, with the changes since the
+/// previous snapshot.
+///
+/// Byte counters are only reported by Linux >= 6.0: the computed rates are zero
+/// on older kernels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MptcpStats {
+    /// The information about the connection.
+    pub info: MptcpInfo,
+    /// The time elapsed since the previous snapshot, zero for the first one.
+    pub elapsed: Duration,
+    /// The number of bytes sent since the previous snapshot.
+    pub bytes_sent: u64,
+    /// The number of bytes received since the previous snapshot.
+    pub bytes_received: u64,
+    /// The number of bytes retransmitted since the previous snapshot.
+    pub bytes_retrans: u64,
+    /// The sending throughput since the previous snapshot, in bytes per second.
+    pub send_rate: f64,
+    /// The receiving throughput since the previous snapshot, in bytes per second.
+    pub recv_rate: f64,
+    /// The ratio of retransmitted bytes to sent bytes since the previous snapshot.
+    pub retrans_rate: f64,
+    /// The change of the number of additional subflows since the previous snapshot.
+    pub subflows_delta: i16,
+}
+
+impl MptcpStats {
+    pub(crate) fn first(info: MptcpInfo) -> Self {
+        Self::new(&info, info, Duration::ZERO)
+    }
+
+    pub(crate) fn new(prev: &MptcpInfo, info: MptcpInfo, elapsed: Duration) -> Self {
+        let bytes_sent = info.bytes_sent.saturating_sub(prev.bytes_sent);
+        let bytes_received = info.bytes_received.saturating_sub(prev.bytes_received);
+        let bytes_retrans = info.bytes_retrans.saturating_sub(prev.bytes_retrans);
+
+        let rate = |bytes: u64| {
+            if elapsed.is_zero() {
+                0.0
+            } else {
+                bytes as f64 / elapsed.as_secs_f64()
+            }
+        };
+
+        Self {
+            info,
+            elapsed,
+            bytes_sent,
+            bytes_received,
+            bytes_retrans,
+            send_rate: rate(bytes_sent),
+            recv_rate: rate(bytes_received),
+            retrans_rate: if bytes_sent == 0 {
+                0.0
+            } else {
+                bytes_retrans as f64 / bytes_sent as f64
+            },
+            subflows_delta: info.subflows as i16 - prev.subflows as i16,
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+pub(crate) mod watch {
+    use std::{
+        io,
+        mem::MaybeUninit,
+        os::fd::{AsRawFd, RawFd},
+        time::Duration,
+    };
+
+    use tokio::{
+        runtime::Handle,
+        sync::watch,
+        time::{interval_at, Instant, MissedTickBehavior},
+    };
+
+    use super::MptcpStats;
+    use crate::{sys::MptcpSocketRef, MptcpInfo};
+
+    /// A file descriptor that may be closed by its owner while being watched.
+    ///
+    /// It does not keep the socket open: the file descriptor may be reused by another
+    /// file at any time, so every read is checked against the identity of the socket.
+    struct WatchedFd(RawFd);
+
+    impl AsRawFd for WatchedFd {
+        fn as_raw_fd(&self) -> RawFd {
+            self.0
+        }
+    }
+
+    impl WatchedFd {
+        /// Returns the device and inode of the socket, to detect that the file
+        /// descriptor was closed and reused.
+        fn identity(&self) -> io::Result<(libc::dev_t, libc::ino_t)> {
+            let mut stat = MaybeUninit::<libc::stat>::uninit();
+            match unsafe { libc::fstat(self.0, stat.as_mut_ptr()) } {
+                -1 => Err(io::Error::last_os_error()),
+                _ => {
+                    let stat = unsafe { stat.assume_init() };
+                    Ok((stat.st_dev, stat.st_ino))
+                }
+            }
+        }
+
+        /// Returns the `MptcpInfo` of the socket, if the file descriptor still refers
+        /// to the socket with the given identity once it has been read.
+        fn info(&self, identity: (libc::dev_t, libc::ino_t)) -> io::Result<MptcpInfo> {
+            let info = MptcpSocketRef::from(self).info()?;
+            if self.identity()? != identity {
+                return Err(io::Error::from_raw_os_error(libc::EBADF));
+            }
+            Ok(info)
+        }
+    }
+
+    pub(crate) fn watch_stats(
+        fd: RawFd,
+        interval: Duration,
+    ) -> io::Result<watch::Receiver<MptcpStats>> {
+        if interval.is_zero() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "cannot watch stats with a zero interval",
+            ));
+        }
+
+        let handle = Handle::try_current().map_err(io::Error::other)?;
+        let fd = WatchedFd(fd);
+        let identity = fd.identity()?;
+        let mut prev = fd.info(identity)?;
+        let (tx, rx) = watch::channel(MptcpStats::first(prev));
+
+        handle.spawn(async move {
+            let mut last = Instant::now();
+            let mut ticker = interval_at(last + interval, interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                ticker.tick().await;
+                if tx.is_closed() {
+                    return;
+                }
+
+                // The socket was closed, or its file descriptor reused
+                let Ok(info) = fd.info(identity) else {
+                    return;
+                };
+                let now = Instant::now();
+                tx.send_replace(MptcpStats::new(&prev, info, now - last));
+                prev = info;
+                last = now;
+            }
+        });
+
+        Ok(rx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_deltas() {
+        let prev = MptcpInfo {
+            subflows: 1,
+            bytes_sent: 1000,
+            bytes_received: 500,
+            bytes_retrans: 0,
+            ..Default::default()
+        };
+        let info = MptcpInfo {
+            subflows: 0,
+            bytes_sent: 3000,
+            bytes_received: 1500,
+            bytes_retrans: 100,
+            ..Default::default()
+        };
+
+        let stats = MptcpStats::new(&prev, info, Duration::from_millis(500));
+        assert_eq!(stats.bytes_sent, 2000);
+        assert_eq!(stats.bytes_received, 1000);
+        assert_eq!(stats.send_rate, 4000.0);
+        assert_eq!(stats.recv_rate, 2000.0);
+        assert_eq!(stats.retrans_rate, 0.05);
+        assert_eq!(stats.subflows_delta, -1);
+
+        let stats = MptcpStats::first(info);
+        assert_eq!(stats.bytes_sent, 0);
+        assert_eq!(stats.send_rate, 0.0);
+    }
+}
@@ -1,9 +1,17 @@
 use std::{
-    io,
+    io::{self, IoSlice, IoSliceMut, Read, Write},
     net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    os::fd::{AsFd, AsRawFd, BorrowedFd, RawFd},
+    time::{Duration, Instant},
 };
 
-use crate::{sys::MptcpSocketBuilder, MptcpExt, MptcpOpt, MptcpSocket};
+use socket2::Socket;
+
+use crate::{
+    telemetry::{self, Attempt, Fallback},
+    AcceptFilter, ConnectOptions, FallbackCache, ListenOptions, MptcpExt, MptcpInfo, MptcpOpt,
+    MptcpSocket, MptcpSocketBuilder, MptcpStatus, MptcpSubflow,
+};
 
 /// Extension trait for std::net::TcpStream to support MPTCP.
 pub trait MptcpStreamExt {
@@ -54,6 +62,114 @@ pub trait MptcpStreamExt {
     fn connect_mptcp_force<A: ToSocketAddrs>(addr: A) -> io::Result<Self::Output> {
         Ok(Self::connect_mptcp_opt(addr, MptcpOpt::NoFallback)?.into_socket())
     }
+
+    /// Establishes an MPTCP connection with the given address and MptcpOpt, with a timeout.
+    ///
+    /// Unlike `connect_mptcp_opt`, this does not block for the whole SYN retry period of
+    /// the kernel: the resolved addresses are tried until `timeout` has elapsed. In case
+    /// of fallback, the TCP connection is only given the time left, so the whole call
+    /// never takes much longer than `timeout`.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address to connect to.
+    /// * `timeout` - The maximum time to wait for the connection, fallback included.
+    /// * `opt` - The MptcpOpt options for the connection.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing the MptcpSocket if the connection is successful,
+    /// or an `io::Error` if an error occurs during the connection. An error of kind
+    /// `io::ErrorKind::InvalidInput` is returned if `timeout` is zero.
+    fn connect_mptcp_timeout<A: ToSocketAddrs>(
+        addr: A,
+        timeout: Duration,
+        opt: MptcpOpt,
+    ) -> io::Result<MptcpSocket<Self::Output>>;
+
+    /// Establishes a connection with the given address, using `cache` to skip MPTCP
+    /// for destinations known to fall back to TCP.
+    ///
+    /// Destinations found in the cache are connected to with plain TCP directly. Otherwise
+    /// an MPTCP connection is attempted, falling back to TCP if MPTCP cannot be used, and
+    /// the destination is added to the cache if the established connection reports
+    /// a fallback to TCP.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address to connect to.
+    /// * `cache` - The cache of destinations falling back to TCP.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing the MptcpSocket if the connection is successful,
+    /// or an `io::Error` if an error occurs during the connection.
+    fn connect_mptcp_cached<A: ToSocketAddrs>(
+        addr: A,
+        cache: &FallbackCache,
+    ) -> io::Result<MptcpSocket<Self::Output>>;
+
+    /// Establishes an MPTCP connection with the given address and MptcpOpt, starting the
+    /// initial subflow from the `local` address. The TCP connection used in case of
+    /// fallback is bound to the same address.
+    ///
+    /// # Arguments
+    ///
+    /// * `local` - The local address to bind to before connecting.
+    /// * `addr` - The address to connect to.
+    /// * `opt` - The MptcpOpt options for the connection.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing the MptcpSocket if the connection is successful,
+    /// or an `io::Error` if an error occurs during the connection.
+    fn connect_mptcp_from<A: ToSocketAddrs>(
+        local: SocketAddr,
+        addr: A,
+        opt: MptcpOpt,
+    ) -> io::Result<MptcpSocket<Self::Output>>;
+
+    /// Establishes an MPTCP connection with the given address and MptcpOpt, starting the
+    /// initial subflow on the given network interface using `SO_BINDTODEVICE`. The TCP
+    /// connection used in case of fallback is bound to the same interface.
+    ///
+    /// Only supported on Linux.
+    ///
+    /// # Arguments
+    ///
+    /// * `interface` - The name of the network interface to use.
+    /// * `addr` - The address to connect to.
+    /// * `opt` - The MptcpOpt options for the connection.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing the MptcpSocket if the connection is successful,
+    /// or an `io::Error` if an error occurs during the connection.
+    fn connect_mptcp_from_device<A: ToSocketAddrs>(
+        interface: &str,
+        addr: A,
+        opt: MptcpOpt,
+    ) -> io::Result<MptcpSocket<Self::Output>>;
+
+    /// Establishes an MPTCP connection with the given address and ConnectOptions. If
+    /// MPTCP cannot be used the connection will fallback to a regular TCP connection,
+    /// using the same options.
+    ///
+    /// Use `MptcpSocketBuilder` to configure a connection that does not fall back to TCP.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address to connect to.
+    /// * `options` - The options of the connection.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing the MptcpSocket if the connection is successful,
+    /// or an `io::Error` if an error occurs during the connection.
+    fn connect_mptcp_with<A: ToSocketAddrs>(
+        addr: A,
+        options: ConnectOptions,
+    ) -> io::Result<MptcpSocket<Self::Output>>;
 }
 
 /// Extension trait for std::net::TcpListener.
@@ -102,13 +218,149 @@ pub trait MptcpListenerExt {
     fn bind_mptcp_force<A: ToSocketAddrs>(addr: A) -> io::Result<Self::Output> {
         Ok(Self::bind_mptcp_opt(addr, MptcpOpt::NoFallback)?.into_socket())
     }
+
+    /// Binds an MPTCP socket to the specified address with the given ListenOptions. If
+    /// MPTCP cannot be used the listener will fallback to a regular TCP listener, using
+    /// the same options.
+    ///
+    /// Use `MptcpSocketBuilder` to configure a listener that does not fall back to TCP.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address to bind the socket to.
+    /// * `options` - The options of the listener.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing the MptcpSocket with the default MptcpOpt (Fallback).
+    fn bind_mptcp_with<A: ToSocketAddrs>(
+        addr: A,
+        options: ListenOptions,
+    ) -> io::Result<MptcpSocket<Self::Output>>;
+
+    /// Binds `shards` MPTCP sockets to the specified address with SO_REUSEPORT, so that
+    /// each listener can be served by its own worker thread. If MPTCP cannot be used
+    /// the listeners will fallback to regular TCP listeners.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address to bind the sockets to.
+    /// * `shards` - The number of listeners to create.
+    /// * `opt` - The MptcpOpt to use for the sockets.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing the listeners, which all use MPTCP or all use TCP.
+    fn bind_mptcp_sharded<A: ToSocketAddrs>(
+        addr: A,
+        shards: usize,
+        opt: MptcpOpt,
+    ) -> io::Result<Vec<MptcpSocket<Self::Output>>>;
+
+    /// Accepts a new incoming connection from this listener.
+    ///
+    /// The connection is returned as `MptcpSocket::Mptcp` only if the peer negotiated
+    /// MPTCP, and as `MptcpSocket::Tcp` otherwise, including when the listener itself
+    /// is a TCP listener. Fallbacks can only be detected on Linux >= 5.16.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing the connection and the address of the peer.
+    fn accept_mptcp(&self) -> io::Result<(MptcpSocket<TcpStream>, SocketAddr)>;
+
+    /// Accepts a new incoming connection using MPTCP from this listener.
+    ///
+    /// Connections that do not use MPTCP are rejected by `filter`, and this method keeps
+    /// waiting for the next connection.
+    /// Returns `io::ErrorKind::Unsupported` on Linux < 5.16, where fallbacks cannot
+    /// be detected.
+    ///
+    /// # Arguments
+    ///
+    /// * `filter` - The AcceptFilter handling rejected connections.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing the connection and the address of the peer.
+    fn accept_mptcp_filtered(
+        &self,
+        filter: &AcceptFilter<TcpStream>,
+    ) -> io::Result<(TcpStream, SocketAddr)>;
+
+    /// Returns an iterator over the connections received on this listener, as returned
+    /// by `accept_mptcp`. The iterator never returns `None`.
+    fn incoming_mptcp(&self) -> IncomingMptcp<'_>;
+}
+
+/// Extension trait for MptcpSocketBuilder to create std::net sockets.
+pub trait MptcpSocketBuilderExt {
+    /// Establishes an MPTCP connection with the given address, using the options of
+    /// the builder.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address to connect to.
+    /// * `opt` - The MptcpOpt options for the connection.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing the MptcpSocket if the connection is successful,
+    /// or an `io::Error` if an error occurs during the connection.
+    fn connect<A: ToSocketAddrs>(
+        &self,
+        addr: A,
+        opt: MptcpOpt,
+    ) -> io::Result<MptcpSocket<TcpStream>>;
+
+    /// Binds an MPTCP socket to the specified address, using the options of the builder.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address to bind the socket to.
+    /// * `opt` - The MptcpOpt to use for the socket.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing the MptcpSocket with the specified MptcpOpt.
+    fn bind<A: ToSocketAddrs>(
+        &self,
+        addr: A,
+        opt: MptcpOpt,
+    ) -> io::Result<MptcpSocket<TcpListener>>;
+
+    /// Binds `shards` MPTCP sockets to the specified address with SO_REUSEPORT, using the
+    /// options of the builder.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address to bind the sockets to.
+    /// * `shards` - The number of listeners to create.
+    /// * `opt` - The MptcpOpt to use for the sockets.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing the listeners, which all use MPTCP or all use TCP.
+    fn bind_sharded<A: ToSocketAddrs>(
+        &self,
+        addr: A,
+        shards: usize,
+        opt: MptcpOpt,
+    ) -> io::Result<Vec<MptcpSocket<TcpListener>>>;
+}
+
+fn resolve_each_addr<A: ToSocketAddrs, F, T>(addr: &A, attempt: Attempt, mut f: F) -> io::Result<T>
+where
+    F: FnMut(SocketAddr) -> io::Result<T>,
+{
+    let addrs = attempt.resolve(|| addr.to_socket_addrs())?;
+    each_addr(addrs, |addr| attempt.run(addr, &mut f))
 }
 
-fn resolve_each_addr<A: ToSocketAddrs, F, T>(addr: &A, mut f: F) -> io::Result<T>
+fn each_addr<I, F, T>(addrs: I, mut f: F) -> io::Result<T>
 where
+    I: IntoIterator<Item = SocketAddr>,
     F: FnMut(SocketAddr) -> io::Result<T>,
 {
-    let addrs = addr.to_socket_addrs()?;
     let mut last_err = None;
     for addr in addrs {
         match f(addr) {
@@ -124,104 +376,662 @@ where
     }))
 }
 
-impl MptcpStreamExt for TcpStream {
-    type Output = Self;
+/// Returns the time left before `deadline`, or an error once it has passed.
+fn remaining(deadline: Instant) -> io::Result<Duration> {
+    match deadline.saturating_duration_since(Instant::now()) {
+        remaining if remaining.is_zero() => Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "connection timed out",
+        )),
+        remaining => Ok(remaining),
+    }
+}
 
-    fn connect_mptcp_opt<A: ToSocketAddrs>(
+fn wait_connected(sock: &Socket, deadline: Instant) -> io::Result<()> {
+    let mut pollfd = libc::pollfd {
+        fd: sock.as_raw_fd(),
+        events: libc::POLLOUT,
+        revents: 0,
+    };
+
+    loop {
+        let timeout_ms = remaining(deadline)?
+            .as_millis()
+            .clamp(1, libc::c_int::MAX as u128) as libc::c_int;
+        match unsafe { libc::poll(&mut pollfd, 1, timeout_ms) } {
+            -1 => {
+                let err = io::Error::last_os_error();
+                if err.kind() != io::ErrorKind::Interrupted {
+                    return Err(err);
+                }
+            }
+            0 => {}
+            _ => {
+                if let Some(err) = sock.take_error()? {
+                    return Err(err);
+                }
+                if pollfd.revents & (libc::POLLHUP | libc::POLLERR) != 0 {
+                    return Err(io::Error::other("no error set after POLLHUP"));
+                }
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn connect_mptcp_addr(builder: &MptcpSocketBuilder, addr: SocketAddr) -> io::Result<Socket> {
+    let sock = builder.connect_mptcp(addr, false)?;
+    telemetry::connected(&sock);
+    Ok(sock)
+}
+
+fn connect_mptcp_addr_timeout(addr: SocketAddr, deadline: Instant) -> io::Result<TcpStream> {
+    let sock = MptcpSocketBuilder::new().connect_mptcp(addr, true)?;
+    wait_connected(&sock, deadline)?;
+    telemetry::connected(&sock);
+    sock.set_nonblocking(false)?;
+    Ok(sock.into())
+}
+
+impl MptcpSocketBuilderExt for MptcpSocketBuilder {
+    fn connect<A: ToSocketAddrs>(
+        &self,
         addr: A,
         opt: MptcpOpt,
-    ) -> io::Result<MptcpSocket<Self::Output>> {
-        match resolve_each_addr(&addr, |addr| {
-            MptcpSocketBuilder::new_for_addr(addr)?.connect(addr)
+    ) -> io::Result<MptcpSocket<TcpStream>> {
+        match resolve_each_addr(&addr, Attempt::mptcp("connect"), |addr| {
+            connect_mptcp_addr(self, addr)
         }) {
             Ok(sock) => Ok(MptcpSocket::Mptcp(sock.into())),
-            Err(_) if matches!(opt, MptcpOpt::Fallback) => {
-                Ok(MptcpSocket::Tcp(Self::connect(addr)?))
+            Err(err) if matches!(opt, MptcpOpt::Fallback) => {
+                telemetry::fallback("connect", Fallback::Error(&err));
+                Ok(MptcpSocket::Tcp(
+                    resolve_each_addr(&addr, Attempt::fallback("connect"), |addr| {
+                        self.connect_tcp(addr, false)
+                    })?
+                    .into(),
+                ))
             }
             Err(err) => Err(err),
         }
     }
-}
 
-impl MptcpExt for TcpStream {}
+    fn bind<A: ToSocketAddrs>(
+        &self,
+        addr: A,
+        opt: MptcpOpt,
+    ) -> io::Result<MptcpSocket<TcpListener>> {
+        match resolve_each_addr(&addr, Attempt::mptcp("bind"), |addr| {
+            self.bind_mptcp(addr, false)
+        }) {
+            Ok(sock) => Ok(MptcpSocket::Mptcp(sock.into())),
+            Err(err) if matches!(opt, MptcpOpt::Fallback) => {
+                telemetry::fallback("bind", Fallback::Error(&err));
+                Ok(MptcpSocket::Tcp(
+                    resolve_each_addr(&addr, Attempt::fallback("bind"), |addr| {
+                        self.bind_tcp(addr, false)
+                    })?
+                    .into(),
+                ))
+            }
+            Err(err) => Err(err),
+        }
+    }
 
-impl From<MptcpSocket<TcpStream>> for TcpStream {
-    fn from(socket: MptcpSocket<TcpStream>) -> Self {
-        socket.into_socket()
+    fn bind_sharded<A: ToSocketAddrs>(
+        &self,
+        addr: A,
+        shards: usize,
+        opt: MptcpOpt,
+    ) -> io::Result<Vec<MptcpSocket<TcpListener>>> {
+        match resolve_each_addr(&addr, Attempt::mptcp("bind"), |addr| {
+            self.bind_shards(addr, shards, |builder, addr| {
+                builder.bind_mptcp(addr, false)
+            })
+        }) {
+            Ok(socks) => Ok(socks
+                .into_iter()
+                .map(|sock| MptcpSocket::Mptcp(sock.into()))
+                .collect()),
+            Err(err) if matches!(opt, MptcpOpt::Fallback) => {
+                telemetry::fallback("bind", Fallback::Error(&err));
+                Ok(resolve_each_addr(&addr, Attempt::fallback("bind"), |addr| {
+                    self.bind_shards(addr, shards, |builder, addr| builder.bind_tcp(addr, false))
+                })?
+                .into_iter()
+                .map(|sock| MptcpSocket::Tcp(sock.into()))
+                .collect())
+            }
+            Err(err) => Err(err),
+        }
     }
 }
 
-impl MptcpListenerExt for TcpListener {
+impl MptcpStreamExt for TcpStream {
     type Output = Self;
 
-    fn bind_mptcp_opt<A: ToSocketAddrs>(
+    fn connect_mptcp_opt<A: ToSocketAddrs>(
+        addr: A,
+        opt: MptcpOpt,
+    ) -> io::Result<MptcpSocket<Self::Output>> {
+        MptcpSocketBuilder::new().connect(addr, opt)
+    }
+
+    fn connect_mptcp_timeout<A: ToSocketAddrs>(
         addr: A,
+        timeout: Duration,
         opt: MptcpOpt,
     ) -> io::Result<MptcpSocket<Self::Output>> {
-        match resolve_each_addr(&addr, |addr| {
-            MptcpSocketBuilder::new_for_addr(addr)?.bind(addr)
+        if timeout.is_zero() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "cannot set a 0 duration timeout",
+            ));
+        }
+
+        let deadline = Instant::now() + timeout;
+        match resolve_each_addr(&addr, Attempt::mptcp("connect"), |addr| {
+            connect_mptcp_addr_timeout(addr, deadline)
         }) {
-            Ok(sock) => Ok(MptcpSocket::Mptcp(sock.into())),
-            Err(_) if matches!(opt, MptcpOpt::Fallback) => Ok(MptcpSocket::Tcp(Self::bind(addr)?)),
+            Ok(sock) => Ok(MptcpSocket::Mptcp(sock)),
+            Err(err) if matches!(opt, MptcpOpt::Fallback) => {
+                telemetry::fallback("connect", Fallback::Error(&err));
+                Ok(MptcpSocket::Tcp(resolve_each_addr(
+                    &addr,
+                    Attempt::fallback("connect"),
+                    |addr| Self::connect_timeout(&addr, remaining(deadline)?),
+                )?))
+            }
             Err(err) => Err(err),
         }
     }
+
+    fn connect_mptcp_cached<A: ToSocketAddrs>(
+        addr: A,
+        cache: &FallbackCache,
+    ) -> io::Result<MptcpSocket<Self::Output>> {
+        let addrs = Attempt::mptcp("connect").resolve(|| addr.to_socket_addrs())?;
+        each_addr(addrs, |addr| {
+            if cache.contains(&addr) {
+                telemetry::fallback("connect", Fallback::Cached);
+            } else {
+                let attempt = Attempt::mptcp("connect");
+                match attempt.run(addr, |addr| {
+                    connect_mptcp_addr(&MptcpSocketBuilder::new(), addr)
+                }) {
+                    Ok(sock) => {
+                        let sock = sock.into();
+                        cache.record(addr, &sock);
+                        return Ok(MptcpSocket::Mptcp(sock));
+                    }
+                    Err(err) => telemetry::fallback("connect", Fallback::Error(&err)),
+                }
+            }
+            Attempt::fallback("connect")
+                .run(addr, Self::connect)
+                .map(MptcpSocket::Tcp)
+        })
+    }
+
+    fn connect_mptcp_from<A: ToSocketAddrs>(
+        local: SocketAddr,
+        addr: A,
+        opt: MptcpOpt,
+    ) -> io::Result<MptcpSocket<Self::Output>> {
+        MptcpSocketBuilder::new()
+            .local_addr(local)
+            .connect(addr, opt)
+    }
+
+    fn connect_mptcp_from_device<A: ToSocketAddrs>(
+        interface: &str,
+        addr: A,
+        opt: MptcpOpt,
+    ) -> io::Result<MptcpSocket<Self::Output>> {
+        MptcpSocketBuilder::new()
+            .bind_device(interface)
+            .connect(addr, opt)
+    }
+
+    fn connect_mptcp_with<A: ToSocketAddrs>(
+        addr: A,
+        options: ConnectOptions,
+    ) -> io::Result<MptcpSocket<Self::Output>> {
+        MptcpSocketBuilder::from(options).connect(addr, MptcpOpt::Fallback)
+    }
 }
 
-impl From<MptcpSocket<TcpListener>> for TcpListener {
-    fn from(socket: MptcpSocket<TcpListener>) -> Self {
+impl MptcpExt for TcpStream {}
+
+impl From<MptcpSocket<TcpStream>> for TcpStream {
+    fn from(socket: MptcpSocket<TcpStream>) -> Self {
         socket.into_socket()
     }
 }
 
-#[cfg(all(test, target_os = "linux"))]
-mod test {
-    use std::net::{IpAddr, Ipv4Addr};
+/// A `TcpStream` remembering whether it uses MPTCP.
+///
+/// Unlike `MptcpSocket<TcpStream>`, it implements `Read` and `Write`, so it can be
+/// passed to generic code while keeping the MPTCP metadata. The status is checked
+/// when the stream is created: use `MptcpExt::mptcp_status` to detect a later fallback.
+///
+/// # Example
+///
+/// ```rust
+/// use mptcp::{MptcpListenerExt, MptcpStream, MptcpStreamExt};
+/// use std::net::{TcpListener, TcpStream};
+///
+/// let listener = TcpListener::bind_mptcp("127.0.0.1:0").unwrap();
+/// let stream = MptcpStream::from(TcpStream::connect_mptcp(listener.local_addr().unwrap()).unwrap());
+/// println!("stream uses mptcp: {}", stream.is_mptcp());
+/// ```
+#[derive(Debug)]
+pub struct MptcpStream {
+    inner: TcpStream,
+    status: MptcpStatus,
+}
 
-    use crate::sys::is_mptcp_enabled;
+impl MptcpStream {
+    /// Returns the MPTCP status of the stream when it was created.
+    pub fn status(&self) -> MptcpStatus {
+        self.status
+    }
 
-    use super::*;
+    /// Returns whether the stream used MPTCP without fallback when it was created.
+    pub fn is_mptcp(&self) -> bool {
+        self.status.is_mptcp()
+    }
 
-    #[test]
-    fn test_resolve_each_addr() {
-        let addr = "127.0.0.1:80";
-        let result = resolve_each_addr(&addr, |addr| {
-            assert_eq!(addr.port(), 80);
-            assert_eq!(addr.ip(), IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)));
-            Ok(())
-        });
-        assert!(result.is_ok());
+    /// Returns information about the MPTCP connection, see `MptcpExt::mptcp_info`.
+    pub fn info(&self) -> io::Result<MptcpInfo> {
+        self.inner.mptcp_info()
     }
 
-    #[test]
-    fn test_resolve_each_addr_error() {
-        let addr = "thisisanerror";
-        let result = resolve_each_addr(&addr, |_| Ok(()));
-        assert!(result.is_err());
+    /// Returns the addresses of the subflows, see `MptcpExt::mptcp_subflows`.
+    pub fn subflows(&self) -> io::Result<Vec<MptcpSubflow>> {
+        self.inner.mptcp_subflows()
     }
 
-    #[test]
-    fn test_mptcp_socket() {
-        let mptcp_enabled = is_mptcp_enabled();
+    /// Returns a reference to the underlying `TcpStream`.
+    pub fn get_ref(&self) -> &TcpStream {
+        &self.inner
+    }
 
-        let listener = TcpListener::bind_mptcp("127.0.0.1:0");
-        if mptcp_enabled {
-            assert!(matches!(listener, Ok(MptcpSocket::Mptcp(..))));
-        } else {
-            assert!(matches!(listener, Ok(MptcpSocket::Tcp(..))));
-        }
+    /// Consumes the stream, returning the underlying `TcpStream`.
+    pub fn into_inner(self) -> TcpStream {
+        self.inner
+    }
 
-        let listener = listener.unwrap().into_socket();
-        let local_addr = listener.local_addr().unwrap();
+    /// Creates a new handle to the same stream, keeping the MPTCP status. This can be
+    /// used to read and write from different threads.
+    pub fn try_clone(&self) -> io::Result<Self> {
+        Ok(Self {
+            inner: self.inner.try_clone()?,
+            status: self.status,
+        })
+    }
+}
 
-        let stream = TcpStream::connect_mptcp(local_addr);
-        if mptcp_enabled {
-            assert!(matches!(stream, Ok(MptcpSocket::Mptcp(..))));
+impl From<TcpStream> for MptcpStream {
+    fn from(stream: TcpStream) -> Self {
+        let status = stream.mptcp_status();
+        Self {
+            inner: stream,
+            status,
+        }
+    }
+}
+
+impl From<MptcpSocket<TcpStream>> for MptcpStream {
+    fn from(socket: MptcpSocket<TcpStream>) -> Self {
+        socket.into_socket().into()
+    }
+}
+
+impl From<MptcpStream> for TcpStream {
+    fn from(stream: MptcpStream) -> Self {
+        stream.inner
+    }
+}
+
+impl Read for MptcpStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut<'_>]) -> io::Result<usize> {
+        self.inner.read_vectored(bufs)
+    }
+}
+
+impl Read for &MptcpStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        (&self.inner).read(buf)
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut<'_>]) -> io::Result<usize> {
+        (&self.inner).read_vectored(bufs)
+    }
+}
+
+impl Write for MptcpStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        self.inner.write_vectored(bufs)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl Write for &MptcpStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        (&self.inner).write(buf)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        (&self.inner).write_vectored(bufs)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        (&self.inner).flush()
+    }
+}
+
+impl AsRawFd for MptcpStream {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+impl AsFd for MptcpStream {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.inner.as_fd()
+    }
+}
+
+impl MptcpExt for MptcpStream {}
+
+impl MptcpListenerExt for TcpListener {
+    type Output = Self;
+
+    fn bind_mptcp_opt<A: ToSocketAddrs>(
+        addr: A,
+        opt: MptcpOpt,
+    ) -> io::Result<MptcpSocket<Self::Output>> {
+        MptcpSocketBuilder::new().bind(addr, opt)
+    }
+
+    fn bind_mptcp_with<A: ToSocketAddrs>(
+        addr: A,
+        options: ListenOptions,
+    ) -> io::Result<MptcpSocket<Self::Output>> {
+        MptcpSocketBuilder::from(options).bind(addr, MptcpOpt::Fallback)
+    }
+
+    fn bind_mptcp_sharded<A: ToSocketAddrs>(
+        addr: A,
+        shards: usize,
+        opt: MptcpOpt,
+    ) -> io::Result<Vec<MptcpSocket<Self::Output>>> {
+        MptcpSocketBuilder::new().bind_sharded(addr, shards, opt)
+    }
+
+    fn accept_mptcp(&self) -> io::Result<(MptcpSocket<TcpStream>, SocketAddr)> {
+        let (sock, addr) = self.accept()?;
+        Ok((MptcpSocket::from_accepted(sock), addr))
+    }
+
+    fn accept_mptcp_filtered(
+        &self,
+        filter: &AcceptFilter<TcpStream>,
+    ) -> io::Result<(TcpStream, SocketAddr)> {
+        filter.check_supported()?;
+        loop {
+            let (sock, addr) = self.accept()?;
+            if let Some(sock) = filter.filter(sock, addr) {
+                return Ok((sock, addr));
+            }
+        }
+    }
+
+    fn incoming_mptcp(&self) -> IncomingMptcp<'_> {
+        IncomingMptcp { listener: self }
+    }
+}
+
+impl MptcpExt for TcpListener {}
+
+/// An iterator over the connections of a `TcpListener`, see `incoming_mptcp`.
+#[derive(Debug)]
+pub struct IncomingMptcp<'a> {
+    listener: &'a TcpListener,
+}
+
+impl Iterator for IncomingMptcp<'_> {
+    type Item = io::Result<MptcpSocket<TcpStream>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.listener.accept_mptcp().map(|(sock, _)| sock))
+    }
+}
+
+impl From<MptcpSocket<TcpListener>> for TcpListener {
+    fn from(socket: MptcpSocket<TcpListener>) -> Self {
+        socket.into_socket()
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod test {
+    use std::{
+        io::{Read, Write},
+        net::{IpAddr, Ipv4Addr},
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    use socket2::SockRef;
+
+    use crate::{
+        sys::{has_mptcp_info, is_mptcp_enabled},
+        MptcpStatus,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_resolve_each_addr() {
+        let addr = "127.0.0.1:80";
+        let result = resolve_each_addr(&addr, Attempt::mptcp("connect"), |addr| {
+            assert_eq!(addr.port(), 80);
+            assert_eq!(addr.ip(), IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)));
+            Ok(())
+        });
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_resolve_each_addr_error() {
+        let addr = "thisisanerror";
+        let result = resolve_each_addr(&addr, Attempt::mptcp("connect"), |_| Ok(()));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_mptcp_socket() {
+        let mptcp_enabled = is_mptcp_enabled();
+
+        let listener = TcpListener::bind_mptcp("127.0.0.1:0");
+        if mptcp_enabled {
+            assert!(matches!(listener, Ok(MptcpSocket::Mptcp(..))));
+        } else {
+            assert!(matches!(listener, Ok(MptcpSocket::Tcp(..))));
+        }
+
+        let listener = listener.unwrap().into_socket();
+        let local_addr = listener.local_addr().unwrap();
+
+        let stream = TcpStream::connect_mptcp(local_addr);
+        if mptcp_enabled {
+            assert!(matches!(stream, Ok(MptcpSocket::Mptcp(..))));
         } else {
             assert!(matches!(stream, Ok(MptcpSocket::Tcp(..))));
         }
     }
 
+    #[test]
+    fn test_mptcp_listener_status() {
+        let mptcp_enabled = is_mptcp_enabled();
+
+        let listener = TcpListener::bind_mptcp("127.0.0.1:0").unwrap();
+        if mptcp_enabled {
+            assert!(matches!(
+                listener.mptcp_status(),
+                MptcpStatus::Mptcp {
+                    has_fallback: false
+                }
+            ));
+        } else {
+            assert!(matches!(listener.mptcp_status(), MptcpStatus::Tcp));
+        }
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        assert!(matches!(listener.mptcp_status(), MptcpStatus::Tcp));
+    }
+
+    #[test]
+    fn test_accept_mptcp_filtered() {
+        if !is_mptcp_enabled() || !has_mptcp_info() {
+            // Fallback can only be detected on >= 5.16 kernels
+            return;
+        }
+
+        let listener = TcpListener::bind_mptcp("127.0.0.1:0").unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        let rejected = Arc::new(AtomicUsize::new(0));
+        let counter = rejected.clone();
+        let filter = AcceptFilter::with_handler(move |_, _| {
+            counter.fetch_add(1, Ordering::Relaxed);
+        });
+
+        let _tcp = TcpStream::connect(local_addr).unwrap();
+        let mptcp = TcpStream::connect_mptcp(local_addr).unwrap();
+
+        let (accepted, addr) = listener.accept_mptcp_filtered(&filter).unwrap();
+        assert_eq!(addr, mptcp.local_addr().unwrap());
+        assert!(matches!(
+            accepted.mptcp_status(),
+            MptcpStatus::Mptcp {
+                has_fallback: false
+            }
+        ));
+        assert_eq!(filter.rejected(), 1);
+        assert_eq!(rejected.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_accept_mptcp_filtered_unsupported() {
+        let listener = TcpListener::bind_mptcp("127.0.0.1:0").unwrap();
+        let local_addr = listener.local_addr().unwrap();
+        let _stream = TcpStream::connect_mptcp(local_addr).unwrap();
+
+        // Do not wait for another connection if this one is rejected
+        listener.set_nonblocking(true).unwrap();
+        let result = listener.accept_mptcp_filtered(&AcceptFilter::new());
+        if has_mptcp_info() {
+            assert!(result.is_ok() || !is_mptcp_enabled());
+        } else {
+            assert_eq!(result.unwrap_err().kind(), io::ErrorKind::Unsupported);
+        }
+    }
+
+    #[test]
+    fn test_accept_mptcp() {
+        let mptcp_enabled = is_mptcp_enabled();
+
+        let listener = TcpListener::bind_mptcp("127.0.0.1:0").unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        let stream = TcpStream::connect_mptcp(local_addr).unwrap();
+        let (accepted, addr) = listener.accept_mptcp().unwrap();
+        assert_eq!(addr, stream.local_addr().unwrap());
+        if mptcp_enabled {
+            assert!(matches!(accepted, MptcpSocket::Mptcp(..)));
+        } else {
+            assert!(matches!(accepted, MptcpSocket::Tcp(..)));
+        }
+
+        let _stream = TcpStream::connect(local_addr).unwrap();
+        let (accepted, _) = listener.accept_mptcp().unwrap();
+        if !mptcp_enabled || has_mptcp_info() {
+            // Fallback can only be detected on >= 5.16 kernels
+            assert!(matches!(accepted, MptcpSocket::Tcp(..)));
+        }
+    }
+
+    #[test]
+    fn test_incoming_mptcp() {
+        let mptcp_enabled = is_mptcp_enabled();
+
+        let listener = TcpListener::bind_mptcp("127.0.0.1:0").unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        let _streams: Vec<_> = (0..2)
+            .map(|_| TcpStream::connect_mptcp(local_addr).unwrap())
+            .collect();
+
+        for accepted in listener.incoming_mptcp().take(2) {
+            assert_eq!(
+                matches!(accepted.unwrap(), MptcpSocket::Mptcp(..)),
+                mptcp_enabled
+            );
+        }
+    }
+
+    fn echo<S: Read + Write>(mut stream: S) {
+        stream.write_all(b"hello").unwrap();
+        let mut buf = [0; 5];
+        stream.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"hello");
+    }
+
+    #[test]
+    fn test_mptcp_stream() {
+        let mptcp_enabled = is_mptcp_enabled();
+
+        let listener = TcpListener::bind_mptcp("127.0.0.1:0").unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        let stream = MptcpStream::from(TcpStream::connect_mptcp(local_addr).unwrap());
+        let (accepted, _) = listener.accept().unwrap();
+        let accepted = MptcpStream::from(accepted);
+        assert_eq!(stream.is_mptcp(), mptcp_enabled);
+        assert_eq!(accepted.is_mptcp(), mptcp_enabled);
+        assert_eq!(stream.status(), stream.mptcp_status());
+        if mptcp_enabled && has_mptcp_info() {
+            assert!(stream.info().is_ok());
+            assert_eq!(stream.subflows().unwrap().len(), 1);
+        }
+
+        let clone = accepted.try_clone().unwrap();
+        assert_eq!(clone.status(), accepted.status());
+        std::thread::spawn(move || {
+            let mut buf = [0; 5];
+            (&accepted).read_exact(&mut buf).unwrap();
+            (&clone).write_all(&buf).unwrap();
+        });
+
+        echo(stream);
+    }
+
     #[test]
     fn test_mptcp_no_fallback() {
         let mptcp_enabled = is_mptcp_enabled();
@@ -237,4 +1047,605 @@ mod test {
         let stream = TcpStream::connect_mptcp_force("127.0.0.1:0");
         assert!(stream.is_err());
     }
+
+    #[test]
+    fn test_mptcp_dual_stack() {
+        let mptcp_enabled = is_mptcp_enabled();
+
+        if TcpListener::bind("[::1]:0").is_err() {
+            // IPv6 is not available on this system
+            return;
+        }
+
+        let listener = TcpListener::bind_mptcp("[::]:0");
+        if mptcp_enabled {
+            assert!(matches!(listener, Ok(MptcpSocket::Mptcp(..))));
+        } else {
+            assert!(matches!(listener, Ok(MptcpSocket::Tcp(..))));
+        }
+
+        let listener = listener.unwrap().into_socket();
+        let port = listener.local_addr().unwrap().port();
+
+        // IPv4 clients are seen through IPv4-mapped addresses
+        let stream = TcpStream::connect_mptcp(("127.0.0.1", port)).unwrap();
+        let (accepted, peer_addr) = listener.accept().unwrap();
+        let mapped = IpAddr::V6(Ipv4Addr::LOCALHOST.to_ipv6_mapped());
+        assert_eq!(
+            peer_addr,
+            SocketAddr::new(mapped, stream.local_addr().unwrap().port())
+        );
+        assert_eq!(
+            accepted.local_addr().unwrap(),
+            SocketAddr::new(mapped, port)
+        );
+        assert_eq!(
+            stream.peer_addr().unwrap(),
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port)
+        );
+        if mptcp_enabled {
+            assert!(matches!(
+                accepted.mptcp_status(),
+                MptcpStatus::Mptcp {
+                    has_fallback: false
+                }
+            ));
+        }
+
+        // IPv6 clients are accepted as well
+        let stream = TcpStream::connect_mptcp(("::1", port)).unwrap();
+        let (_, peer_addr) = listener.accept().unwrap();
+        assert_eq!(peer_addr, stream.local_addr().unwrap());
+    }
+
+    #[test]
+    fn test_mptcp_v6_only() {
+        if TcpListener::bind("[::1]:0").is_err() {
+            // IPv6 is not available on this system
+            return;
+        }
+
+        let listener = TcpListener::bind_mptcp_with(
+            "[::]:0",
+            ListenOptions {
+                v6_only: Some(true),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        assert!(TcpStream::connect(("127.0.0.1", port)).is_err());
+        assert!(TcpStream::connect(("::1", port)).is_ok());
+    }
+
+    #[test]
+    fn test_builder_connect() {
+        let mptcp_enabled = is_mptcp_enabled();
+
+        let listener = TcpListener::bind_mptcp("127.0.0.1:0")
+            .unwrap()
+            .into_socket();
+        let local_addr = listener.local_addr().unwrap();
+
+        let stream = MptcpSocketBuilder::new()
+            .nodelay(true)
+            .local_addr("127.0.0.2:0".parse().unwrap())
+            .connect(local_addr, MptcpOpt::Fallback);
+        if mptcp_enabled {
+            assert!(matches!(stream, Ok(MptcpSocket::Mptcp(..))));
+        } else {
+            assert!(matches!(stream, Ok(MptcpSocket::Tcp(..))));
+        }
+
+        let stream = stream.unwrap();
+        assert!(stream.nodelay().unwrap());
+        assert_eq!(
+            stream.local_addr().unwrap().ip(),
+            IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2))
+        );
+    }
+
+    #[test]
+    fn test_builder_on_socket() {
+        let mptcp_enabled = is_mptcp_enabled();
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        let counter = calls.clone();
+        let stream = MptcpSocketBuilder::new()
+            .on_socket(move |socket| {
+                counter.fetch_add(1, Ordering::Relaxed);
+                socket.set_tos(0x10)
+            })
+            .connect(local_addr, MptcpOpt::Fallback);
+        if mptcp_enabled {
+            assert!(matches!(stream, Ok(MptcpSocket::Mptcp(..))));
+        } else {
+            assert!(matches!(stream, Ok(MptcpSocket::Tcp(..))));
+        }
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+        assert_eq!(SockRef::from(&*stream.unwrap()).tos().unwrap(), 0x10);
+
+        // The hook is applied to the fallback socket too
+        let stream = MptcpSocketBuilder::new()
+            .on_socket(|_| Err(io::ErrorKind::PermissionDenied.into()))
+            .connect(local_addr, MptcpOpt::Fallback);
+        assert_eq!(
+            stream.err().map(|err| err.kind()),
+            Some(io::ErrorKind::PermissionDenied)
+        );
+    }
+
+    fn has_fast_open_server() -> bool {
+        std::fs::read_to_string("/proc/sys/net/ipv4/tcp_fastopen")
+            .ok()
+            .and_then(|value| value.trim().parse::<i32>().ok())
+            .is_some_and(|value| value & 0x3 == 0x3)
+    }
+
+    fn has_syn_data(stream: &TcpStream) -> bool {
+        // tcpi_options is the 6th byte of struct tcp_info
+        let mut info = [0u8; 8];
+        let mut len = info.len() as libc::socklen_t;
+        let ret = unsafe {
+            libc::getsockopt(
+                stream.as_raw_fd(),
+                libc::IPPROTO_TCP,
+                libc::TCP_INFO,
+                info.as_mut_ptr().cast(),
+                &mut len,
+            )
+        };
+        // TCPI_OPT_SYN_DATA
+        ret == 0 && info[5] & 0x20 != 0
+    }
+
+    #[test]
+    fn test_builder_fast_open() {
+        let mptcp_enabled = is_mptcp_enabled();
+        let builder = MptcpSocketBuilder::new().fast_open(true);
+
+        let listener = builder.bind("127.0.0.1:0", MptcpOpt::Fallback).unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        // The first connection retrieves the TFO cookie, the next one uses it
+        for i in 0..2 {
+            let mut stream = builder.connect(local_addr, MptcpOpt::Fallback).unwrap();
+            if mptcp_enabled {
+                assert!(matches!(stream, MptcpSocket::Mptcp(..)));
+            } else {
+                assert!(matches!(stream, MptcpSocket::Tcp(..)));
+            }
+            stream.write_all(b"hello").unwrap();
+
+            let (mut accepted, _) = listener.accept().unwrap();
+            let mut buf = [0; 5];
+            accepted.read_exact(&mut buf).unwrap();
+            assert_eq!(&buf, b"hello");
+
+            if i == 1 && has_fast_open_server() {
+                assert!(has_syn_data(&stream));
+            }
+        }
+    }
+
+    #[test]
+    fn test_connect_mptcp_with() {
+        let mptcp_enabled = is_mptcp_enabled();
+        let options = ListenOptions {
+            fast_open: true,
+            ..Default::default()
+        };
+
+        let listener = TcpListener::bind_mptcp_with("127.0.0.1:0", options).unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        let options = ConnectOptions {
+            local_addr: Some("127.0.0.2:0".parse().unwrap()),
+            fast_open: true,
+            ..Default::default()
+        };
+        let mut stream = TcpStream::connect_mptcp_with(local_addr, options).unwrap();
+        if mptcp_enabled {
+            assert!(matches!(stream, MptcpSocket::Mptcp(..)));
+        } else {
+            assert!(matches!(stream, MptcpSocket::Tcp(..)));
+        }
+        stream.write_all(b"hello").unwrap();
+
+        let (mut accepted, addr) = listener.accept().unwrap();
+        assert_eq!(addr.ip(), IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2)));
+        let mut buf = [0; 5];
+        accepted.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"hello");
+    }
+
+    #[test]
+    fn test_builder_reuse_port() {
+        let mptcp_enabled = is_mptcp_enabled();
+        let builder = MptcpSocketBuilder::new().reuse_port(true).backlog(1024);
+
+        let listener = builder.bind("127.0.0.1:0", MptcpOpt::Fallback);
+        if mptcp_enabled {
+            assert!(matches!(listener, Ok(MptcpSocket::Mptcp(..))));
+        } else {
+            assert!(matches!(listener, Ok(MptcpSocket::Tcp(..))));
+        }
+
+        let local_addr = listener.unwrap().local_addr().unwrap();
+        let other = builder.bind(local_addr, MptcpOpt::Fallback).unwrap();
+        assert_eq!(other.local_addr().unwrap(), local_addr);
+    }
+
+    #[test]
+    fn test_bind_mptcp_with() {
+        let mptcp_enabled = is_mptcp_enabled();
+        let options = ListenOptions {
+            backlog: 4096,
+            reuse_port: true,
+            ..Default::default()
+        };
+
+        let listener = TcpListener::bind_mptcp_with("127.0.0.1:0", options.clone());
+        if mptcp_enabled {
+            assert!(matches!(listener, Ok(MptcpSocket::Mptcp(..))));
+        } else {
+            assert!(matches!(listener, Ok(MptcpSocket::Tcp(..))));
+        }
+
+        let local_addr = listener.unwrap().local_addr().unwrap();
+        let other = TcpListener::bind_mptcp_with(local_addr, options).unwrap();
+        assert_eq!(other.local_addr().unwrap(), local_addr);
+    }
+
+    #[test]
+    fn test_mptcp_sharded() {
+        let mptcp_enabled = is_mptcp_enabled();
+
+        let listeners =
+            TcpListener::bind_mptcp_sharded("127.0.0.1:0", 4, MptcpOpt::Fallback).unwrap();
+        assert_eq!(listeners.len(), 4);
+        if mptcp_enabled {
+            assert!(listeners
+                .iter()
+                .all(|listener| matches!(listener, MptcpSocket::Mptcp(..))));
+        } else {
+            assert!(listeners
+                .iter()
+                .all(|listener| matches!(listener, MptcpSocket::Tcp(..))));
+        }
+
+        let local_addr = listeners[0].local_addr().unwrap();
+        assert!(listeners
+            .iter()
+            .all(|listener| listener.local_addr().unwrap() == local_addr));
+
+        let _streams: Vec<_> = (0..8)
+            .map(|_| TcpStream::connect_mptcp(local_addr).unwrap())
+            .collect();
+
+        let mut accepted = 0;
+        for listener in &listeners {
+            listener.set_nonblocking(true).unwrap();
+            while listener.accept().is_ok() {
+                accepted += 1;
+            }
+        }
+        assert_eq!(accepted, 8);
+    }
+
+    #[test]
+    fn test_mptcp_sharded_empty() {
+        let listeners = TcpListener::bind_mptcp_sharded("127.0.0.1:0", 0, MptcpOpt::Fallback);
+        assert_eq!(listeners.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_mptcp_from() {
+        let mptcp_enabled = is_mptcp_enabled();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        let stream = TcpStream::connect_mptcp_from(
+            "127.0.0.2:0".parse().unwrap(),
+            local_addr,
+            MptcpOpt::Fallback,
+        );
+        if mptcp_enabled {
+            assert!(matches!(stream, Ok(MptcpSocket::Mptcp(..))));
+        } else {
+            assert!(matches!(stream, Ok(MptcpSocket::Tcp(..))));
+        }
+        assert_eq!(
+            stream.unwrap().local_addr().unwrap().ip(),
+            IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2))
+        );
+    }
+
+    #[test]
+    fn test_mptcp_from_device() {
+        let mptcp_enabled = is_mptcp_enabled();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        let stream = TcpStream::connect_mptcp_from_device("lo", local_addr, MptcpOpt::Fallback);
+        if mptcp_enabled {
+            assert!(matches!(stream, Ok(MptcpSocket::Mptcp(..))));
+        } else {
+            assert!(matches!(stream, Ok(MptcpSocket::Tcp(..))));
+        }
+
+        let stream =
+            TcpStream::connect_mptcp_from_device("nonexistent0", local_addr, MptcpOpt::Fallback);
+        assert!(stream.is_err());
+    }
+
+    #[test]
+    fn test_mptcp_cached() {
+        let mptcp_enabled = is_mptcp_enabled();
+        let cache = FallbackCache::new(16, Duration::from_secs(60));
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        let stream = TcpStream::connect_mptcp_cached(local_addr, &cache).unwrap();
+        if !mptcp_enabled || !has_mptcp_info() {
+            // Fallback can only be detected on >= 5.16 kernels
+            return;
+        }
+        assert!(matches!(stream, MptcpSocket::Mptcp(..)));
+        assert!(cache.contains(&local_addr));
+
+        let stream = TcpStream::connect_mptcp_cached(local_addr, &cache).unwrap();
+        assert!(matches!(stream, MptcpSocket::Tcp(..)));
+    }
+
+    #[test]
+    fn test_mptcp_timeout() {
+        let mptcp_enabled = is_mptcp_enabled();
+
+        let listener = TcpListener::bind_mptcp("127.0.0.1:0")
+            .unwrap()
+            .into_socket();
+        let local_addr = listener.local_addr().unwrap();
+
+        let stream = TcpStream::connect_mptcp_timeout(
+            local_addr,
+            Duration::from_secs(1),
+            MptcpOpt::Fallback,
+        );
+        if mptcp_enabled {
+            assert!(matches!(stream, Ok(MptcpSocket::Mptcp(..))));
+        } else {
+            assert!(matches!(stream, Ok(MptcpSocket::Tcp(..))));
+        }
+
+        // The returned stream must be in blocking mode
+        let mut stream = stream.unwrap().into_socket();
+        let (mut peer, _) = listener.accept().unwrap();
+        peer.write_all(b"ping").unwrap();
+        let mut buf = [0; 4];
+        stream.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"ping");
+    }
+
+    #[test]
+    fn test_mptcp_timeout_zero() {
+        let stream =
+            TcpStream::connect_mptcp_timeout("127.0.0.1:80", Duration::ZERO, MptcpOpt::Fallback);
+        assert!(matches!(stream, Err(err) if err.kind() == io::ErrorKind::InvalidInput));
+    }
+
+    #[test]
+    fn test_mptcp_timeout_refused() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let local_addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        let stream = TcpStream::connect_mptcp_timeout(
+            local_addr,
+            Duration::from_secs(1),
+            MptcpOpt::Fallback,
+        );
+        assert!(matches!(stream, Err(err) if err.kind() == io::ErrorKind::ConnectionRefused));
+    }
+
+    #[test]
+    fn test_mptcp_timeout_deadline() {
+        // A listener whose accept queue is full drops new SYNs, so both the MPTCP
+        // attempt and the TCP fallback would hang until their timeout.
+        let listener = Socket::new(socket2::Domain::IPV4, socket2::Type::STREAM, None).unwrap();
+        listener
+            .bind(&SocketAddr::from((Ipv4Addr::LOCALHOST, 0)).into())
+            .unwrap();
+        listener.listen(0).unwrap();
+        let local_addr = listener.local_addr().unwrap().as_socket().unwrap();
+        let _backlog: Vec<_> = (0..4)
+            .filter_map(|_| {
+                TcpStream::connect_timeout(&local_addr, Duration::from_millis(100)).ok()
+            })
+            .collect();
+
+        let timeout = Duration::from_millis(500);
+        let start = Instant::now();
+        let stream = TcpStream::connect_mptcp_timeout(local_addr, timeout, MptcpOpt::Fallback);
+        let elapsed = start.elapsed();
+        assert!(matches!(stream, Err(err) if err.kind() == io::ErrorKind::TimedOut));
+        assert!(elapsed < timeout * 3 / 2, "took {elapsed:?}");
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_tracing() {
+        use std::sync::Mutex;
+
+        use tracing_subscriber::fmt::format::FmtSpan;
+
+        #[derive(Clone, Default)]
+        struct Output(Arc<Mutex<Vec<u8>>>);
+
+        impl Write for Output {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let capture = |f: &dyn Fn()| {
+            let output = Output::default();
+            let writer = output.clone();
+            let subscriber = tracing_subscriber::fmt()
+                .with_max_level(tracing::Level::DEBUG)
+                .with_span_events(FmtSpan::CLOSE)
+                .with_ansi(false)
+                .with_writer(move || writer.clone())
+                .finish();
+            tracing::subscriber::with_default(subscriber, f);
+            let output = output.0.lock().unwrap().clone();
+            String::from_utf8(output).unwrap()
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let local_addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        let output = capture(&|| assert!(TcpStream::connect_mptcp(local_addr).is_err()));
+        let attempts: Vec<_> = output
+            .lines()
+            .filter(|line| line.contains("mptcp.attempt"))
+            .collect();
+        assert_eq!(attempts.len(), 2, "{output}");
+        assert!(attempts[1].contains(&format!("addr={local_addr}")));
+        assert!(attempts[1].contains("protocol=\"tcp\" fallback=true"));
+        assert!(attempts[1].contains(&format!("errno={}", libc::ECONNREFUSED)));
+        if is_mptcp_enabled() {
+            assert!(attempts[0].contains("protocol=\"mptcp\" fallback=false"));
+            assert!(output.contains("falling back to TCP"));
+            assert!(output.contains("reason=\"error\""));
+        }
+
+        // The peer does not support MPTCP
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let local_addr = listener.local_addr().unwrap();
+        let output = capture(&|| {
+            TcpStream::connect_mptcp(local_addr).unwrap();
+        });
+        if is_mptcp_enabled() && has_mptcp_info() {
+            let attempt = output
+                .lines()
+                .find(|line| line.contains("mptcp.attempt") && line.contains("close"))
+                .unwrap();
+            assert!(attempt.contains("protocol=\"mptcp\" fallback=false"));
+            assert!(attempt.contains("reason=\"peer\""), "{output}");
+            assert!(output.contains("falling back to TCP"));
+        }
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn test_metrics() {
+        use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+
+        let local_addr = metrics::with_local_recorder(&recorder, || {
+            let listener = TcpListener::bind_mptcp("127.0.0.1:0").unwrap();
+            let local_addr = listener.local_addr().unwrap();
+
+            let mut stream = TcpStream::connect_mptcp(local_addr).unwrap();
+            let (mut accepted, _) = listener.accept_mptcp().unwrap();
+            let _tcp = TcpStream::connect(local_addr).unwrap();
+            let _ = listener.accept_mptcp().unwrap();
+
+            stream.write_all(b"hello").unwrap();
+            let mut buf = [0; 5];
+            accepted.read_exact(&mut buf).unwrap();
+            if is_mptcp_enabled() && has_mptcp_info() {
+                assert!(stream.record_mptcp_metrics().is_ok());
+            }
+
+            drop(listener);
+            assert!(TcpStream::connect_mptcp(local_addr).is_err());
+
+            // The peer does not support MPTCP
+            let tcp_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let _peer = TcpStream::connect_mptcp(tcp_listener.local_addr().unwrap()).unwrap();
+
+            local_addr
+        });
+
+        let metrics: Vec<_> = snapshotter
+            .snapshot()
+            .into_vec()
+            .into_iter()
+            .map(|(key, _, _, value)| {
+                let labels: Vec<_> = key
+                    .key()
+                    .labels()
+                    .map(|label| format!("{}={}", label.key(), label.value()))
+                    .collect();
+                (key.key().name().to_string(), labels, value)
+            })
+            .collect();
+        let counter = |name: &str, labels: &[&str]| -> u64 {
+            metrics
+                .iter()
+                .filter(|(n, l, _)| {
+                    n == name && labels.iter().all(|label| l.iter().any(|l| l == label))
+                })
+                .map(|(_, _, value)| match value {
+                    DebugValue::Counter(count) => *count,
+                    _ => 0,
+                })
+                .sum()
+        };
+
+        assert_eq!(counter("mptcp_accepted_total", &[]), 2);
+        assert_eq!(
+            counter("mptcp_attempts_total", &["protocol=tcp", "result=error"]),
+            1
+        );
+        if is_mptcp_enabled() {
+            assert_eq!(counter("mptcp_accepted_total", &["protocol=mptcp"]), 1);
+            // Including the connection the peer made fall back
+            assert_eq!(
+                counter(
+                    "mptcp_attempts_total",
+                    &["op=connect", "protocol=mptcp", "result=ok"]
+                ),
+                2
+            );
+            assert_eq!(
+                counter("mptcp_fallbacks_total", &["op=connect", "reason=error"]),
+                1
+            );
+        }
+        if is_mptcp_enabled() && has_mptcp_info() {
+            assert_eq!(
+                counter("mptcp_fallbacks_total", &["op=connect", "reason=peer"]),
+                1
+            );
+            let subflows = metrics.iter().find(|(name, _, _)| name == "mptcp_subflows");
+            assert!(
+                matches!(subflows, Some((_, _, DebugValue::Histogram(values))) if values[0] == 1.0)
+            );
+            let bytes = metrics
+                .iter()
+                .find(|(name, _, _)| name == "mptcp_subflow_bytes");
+            assert!(matches!(
+                bytes,
+                Some((_, labels, DebugValue::Histogram(values)))
+                    if labels.contains(&format!("remote_addr={local_addr}")) && values[0] == 5.0
+            ));
+        }
+    }
 }
@@ -9,6 +9,8 @@ use std::{
 use socket2::{SockAddr, Socket, Type};
 use sysctl::Sysctl;
 
+use crate::{MptcpInfo, MptcpSubflow};
+
 #[derive(Debug)]
 pub struct MptcpSocketBuilder(Socket);
 
@@ -36,6 +38,14 @@ impl MptcpSocketBuilder {
         Ok(self)
     }
 
+    pub fn socket(&self) -> &Socket {
+        &self.0
+    }
+
+    pub fn into_socket(self) -> Socket {
+        self.0
+    }
+
     pub fn connect(self, addr: SocketAddr) -> io::Result<Socket> {
         let socket = self.0;
         let addr: &SockAddr = &addr.into();
@@ -70,12 +80,24 @@ impl MptcpSocketBuilder {
         }
     }
 
-    pub fn bind(self, _addr: SocketAddr) -> io::Result<Socket> {
+    pub fn bind(self, _addr: SocketAddr, _backlog: i32) -> io::Result<Socket> {
         // bind is not supported for AF_MULTIPATH sockets
         Err(io::ErrorKind::Unsupported.into())
     }
 }
 
+pub fn has_mptcp_info() -> bool {
+    false
+}
+
+pub fn set_fast_open_connect(_socket: &Socket) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+pub fn set_fast_open(_socket: &Socket, _queue_len: i32) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
 pub struct MptcpSocketRef<'a, S>(&'a S);
 
 impl<'a, S: AsRawFd> MptcpSocketRef<'a, S> {
@@ -91,6 +113,18 @@ impl<'a, S: AsRawFd> MptcpSocketRef<'a, S> {
         // No way to check for fallback:
         !self.is_mptcp_socket()
     }
+
+    pub fn info(&self) -> io::Result<MptcpInfo> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    pub fn subflows(&self) -> io::Result<Vec<MptcpSubflow>> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    pub fn subflow_bytes(&self) -> io::Result<Vec<u64>> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
 }
 
 impl<'a, S> From<&'a S> for MptcpSocketRef<'a, S> {
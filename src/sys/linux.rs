@@ -3,13 +3,16 @@ use std::{
     mem::{size_of, MaybeUninit},
     net::SocketAddr,
     os::fd::{AsRawFd, RawFd},
+    ptr,
 };
 
 use semver::Version;
-use socket2::{Domain, Protocol, Socket, Type};
+use socket2::{Domain, Protocol, SockAddr, Socket, Type};
 use sysctl::Sysctl;
 use sysinfo::System;
 
+use crate::MptcpSubflow;
+
 lazy_static::lazy_static! {
     static ref KERNEL_VERSION : Option<Version> = System::kernel_version().and_then(|v| Version::parse(&v).ok());
 }
@@ -31,7 +34,7 @@ impl MptcpSocketBuilder {
     }
 
     pub fn new_v6() -> io::Result<Self> {
-        Self::new(Domain::IPV4)
+        Self::new(Domain::IPV6)
     }
 
     pub fn new_for_addr(addr: SocketAddr) -> io::Result<Self> {
@@ -43,6 +46,14 @@ impl MptcpSocketBuilder {
         Ok(self)
     }
 
+    pub fn socket(&self) -> &Socket {
+        &self.0
+    }
+
+    pub fn into_socket(self) -> Socket {
+        self.0
+    }
+
     pub fn connect(self, addr: SocketAddr) -> io::Result<Socket> {
         let socket = self.0;
 
@@ -55,28 +66,52 @@ impl MptcpSocketBuilder {
         }
     }
 
-    pub fn bind(self, addr: SocketAddr) -> io::Result<Socket> {
+    pub fn bind(self, addr: SocketAddr, backlog: i32) -> io::Result<Socket> {
         let socket = self.0;
         socket.bind(&addr.into())?;
-        socket.listen(0)?;
+        socket.listen(backlog)?;
         Ok(socket)
     }
 }
 
+/// Sets `TCP_FASTOPEN_CONNECT`, so that connecting returns immediately when a TFO
+/// cookie is known for the destination, the data of the first write being sent in
+/// the SYN.
+pub fn set_fast_open_connect(socket: &Socket) -> io::Result<()> {
+    unsafe {
+        setsockopt(
+            socket.as_raw_fd(),
+            libc::IPPROTO_TCP,
+            libc::TCP_FASTOPEN_CONNECT,
+            &(1 as libc::c_int),
+        )
+    }
+}
+
+/// Sets `TCP_FASTOPEN` on a listener, with the maximum number of pending TFO
+/// requests.
+pub fn set_fast_open(socket: &Socket, queue_len: i32) -> io::Result<()> {
+    unsafe {
+        setsockopt(
+            socket.as_raw_fd(),
+            libc::IPPROTO_TCP,
+            libc::TCP_FASTOPEN,
+            &(queue_len as libc::c_int),
+        )
+    }
+}
+
 pub struct MptcpSocketRef<'a, S>(&'a S);
 
 impl<'a, S: AsRawFd> MptcpSocketRef<'a, S> {
     pub fn is_mptcp_socket(&self) -> bool {
         unsafe {
             getsockopt::<libc::c_int>(self.0.as_raw_fd(), libc::SOL_SOCKET, libc::SO_PROTOCOL)
-                .map_or(false, |v| v == libc::IPPROTO_MPTCP)
+                .is_ok_and(|v| v == libc::IPPROTO_MPTCP)
         }
     }
 
     pub fn has_fallback(&self) -> bool {
-        const SOL_MPTCP: libc::c_int = 0x11c;
-        const MPTCP_INFO: libc::c_int = 0x1;
-
         if !has_mptcp_info() {
             // doesn't work for the client side and fallback after established
             return !self.is_mptcp_socket();
@@ -88,9 +123,187 @@ impl<'a, S: AsRawFd> MptcpSocketRef<'a, S> {
                 .is_err()
         }
     }
+
+    pub fn info(&self) -> io::Result<crate::MptcpInfo> {
+        let mut info = MptcpInfo::default();
+        let mut len = size_of::<MptcpInfo>() as libc::socklen_t;
+
+        // Older kernels only fill the beginning of the structure
+        match unsafe {
+            libc::getsockopt(
+                self.0.as_raw_fd(),
+                SOL_MPTCP,
+                MPTCP_INFO,
+                (&mut info as *mut MptcpInfo).cast(),
+                &mut len,
+            )
+        } {
+            -1 => Err(io::Error::last_os_error()),
+            _ => Ok(info.into()),
+        }
+    }
+
+    pub fn subflows(&self) -> io::Result<Vec<MptcpSubflow>> {
+        Ok(self
+            .subflow_data::<SubflowAddrs>(MPTCP_SUBFLOW_ADDRS)?
+            .into_iter()
+            .filter_map(|addrs| {
+                Some(MptcpSubflow {
+                    local_addr: sockaddr_to_std(addrs.local)?,
+                    remote_addr: sockaddr_to_std(addrs.remote)?,
+                })
+            })
+            .collect())
+    }
+
+    /// Returns the bytes sent and received by each subflow, in the order of `subflows`.
+    pub fn subflow_bytes(&self) -> io::Result<Vec<u64>> {
+        Ok(self
+            .subflow_data::<TcpInfo>(MPTCP_TCPINFO)?
+            .into_iter()
+            .map(|info| info.tcpi_bytes_sent + info.tcpi_bytes_received)
+            .collect())
+    }
+
+    /// Reads the entries of a `struct mptcp_subflow_data` option, one per subflow.
+    ///
+    /// Entries the kernel does not fill, e.g. fields added after its version, are
+    /// zeroed.
+    fn subflow_data<T>(&self, optname: libc::c_int) -> io::Result<Vec<T>> {
+        let header_len = size_of::<SubflowData>();
+        let entry_len = size_of::<T>();
+        let mut capacity = 8;
+
+        loop {
+            let mut buf = vec![0u8; header_len + capacity * entry_len];
+            let header = SubflowData {
+                size_subflow_data: header_len as u32,
+                num_subflows: 0,
+                size_kernel: 0,
+                size_user: entry_len as u32,
+            };
+            let mut len = buf.len() as libc::socklen_t;
+
+            let header = unsafe {
+                ptr::write_unaligned(buf.as_mut_ptr().cast(), header);
+                if libc::getsockopt(
+                    self.0.as_raw_fd(),
+                    SOL_MPTCP,
+                    optname,
+                    buf.as_mut_ptr().cast(),
+                    &mut len,
+                ) == -1
+                {
+                    return Err(io::Error::last_os_error());
+                }
+                ptr::read_unaligned(buf.as_ptr().cast::<SubflowData>())
+            };
+
+            let count = header.num_subflows as usize;
+            if count > capacity {
+                // More subflows were established in the meantime
+                capacity = count;
+                continue;
+            }
+
+            let stride = (header.size_user as usize).min(entry_len);
+            return Ok((0..count)
+                .map(|i| {
+                    let mut entry = MaybeUninit::<T>::zeroed();
+                    let offset = header_len + i * header.size_user as usize;
+                    unsafe {
+                        ptr::copy_nonoverlapping(
+                            buf.as_ptr().add(offset),
+                            entry.as_mut_ptr().cast(),
+                            stride,
+                        );
+                        entry.assume_init()
+                    }
+                })
+                .collect());
+        }
+    }
 }
 
-#[derive(Debug)]
+fn sockaddr_to_std(storage: libc::sockaddr_storage) -> Option<SocketAddr> {
+    let len = size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+    unsafe { SockAddr::new(storage, len) }.as_socket()
+}
+
+const SOL_MPTCP: libc::c_int = 0x11c;
+const MPTCP_INFO: libc::c_int = 0x1;
+const MPTCP_TCPINFO: libc::c_int = 0x2;
+const MPTCP_SUBFLOW_ADDRS: libc::c_int = 0x3;
+
+#[repr(C)]
+struct SubflowData {
+    size_subflow_data: u32,
+    num_subflows: u32,
+    size_kernel: u32,
+    size_user: u32,
+}
+
+#[repr(C)]
+struct SubflowAddrs {
+    local: libc::sockaddr_storage,
+    remote: libc::sockaddr_storage,
+}
+
+/// The beginning of `struct tcp_info`, up to the bytes sent.
+#[repr(C)]
+struct TcpInfo {
+    tcpi_state: u8,
+    tcpi_ca_state: u8,
+    tcpi_retransmits: u8,
+    tcpi_probes: u8,
+    tcpi_backoff: u8,
+    tcpi_options: u8,
+    tcpi_snd_rcv_wscale: u8,
+    tcpi_flags: u8,
+    tcpi_rto: u32,
+    tcpi_ato: u32,
+    tcpi_snd_mss: u32,
+    tcpi_rcv_mss: u32,
+    tcpi_unacked: u32,
+    tcpi_sacked: u32,
+    tcpi_lost: u32,
+    tcpi_retrans: u32,
+    tcpi_fackets: u32,
+    tcpi_last_data_sent: u32,
+    tcpi_last_ack_sent: u32,
+    tcpi_last_data_recv: u32,
+    tcpi_last_ack_recv: u32,
+    tcpi_pmtu: u32,
+    tcpi_rcv_ssthresh: u32,
+    tcpi_rtt: u32,
+    tcpi_rttvar: u32,
+    tcpi_snd_ssthresh: u32,
+    tcpi_snd_cwnd: u32,
+    tcpi_advmss: u32,
+    tcpi_reordering: u32,
+    tcpi_rcv_rtt: u32,
+    tcpi_rcv_space: u32,
+    tcpi_total_retrans: u32,
+    tcpi_pacing_rate: u64,
+    tcpi_max_pacing_rate: u64,
+    tcpi_bytes_acked: u64,
+    tcpi_bytes_received: u64,
+    tcpi_segs_out: u32,
+    tcpi_segs_in: u32,
+    tcpi_notsent_bytes: u32,
+    tcpi_min_rtt: u32,
+    tcpi_data_segs_in: u32,
+    tcpi_data_segs_out: u32,
+    tcpi_delivery_rate: u64,
+    tcpi_busy_time: u64,
+    tcpi_rwnd_limited: u64,
+    tcpi_sndbuf_limited: u64,
+    tcpi_delivered: u32,
+    tcpi_delivered_ce: u32,
+    tcpi_bytes_sent: u64,
+}
+
+#[derive(Debug, Default)]
 #[repr(C)]
 struct MptcpInfo {
     mptcpi_subflows: u8,
@@ -119,6 +332,36 @@ struct MptcpInfo {
     mptcpi_last_ack_recv: u32,
 }
 
+impl From<MptcpInfo> for crate::MptcpInfo {
+    fn from(info: MptcpInfo) -> Self {
+        Self {
+            subflows: info.mptcpi_subflows,
+            add_addr_signal: info.mptcpi_add_addr_signal,
+            add_addr_accepted: info.mptcpi_add_addr_accepted,
+            subflows_max: info.mptcpi_subflows_max,
+            add_addr_signal_max: info.mptcpi_add_addr_signal_max,
+            add_addr_accepted_max: info.mptcpi_add_addr_accepted_max,
+            flags: info.mptcpi_flags,
+            token: info.mptcpi_token,
+            write_seq: info.mptcpi_write_seq,
+            snd_una: info.mptcpi_snd_una,
+            rcv_nxt: info.mptcpi_rcv_nxt,
+            local_addr_used: info.mptcpi_local_addr_used,
+            local_addr_max: info.mptcpi_local_addr_max,
+            csum_enabled: info.mptcpi_csum_enabled != 0,
+            retransmits: info.mptcpi_retransmits,
+            bytes_retrans: info.mptcpi_bytes_retrans,
+            bytes_sent: info.mptcpi_bytes_sent,
+            bytes_received: info.mptcpi_bytes_received,
+            bytes_acked: info.mptcpi_bytes_acked,
+            subflows_total: info.mptcpi_subflows_total,
+            last_data_sent: info.mptcpi_last_data_sent,
+            last_data_recv: info.mptcpi_last_data_recv,
+            last_ack_recv: info.mptcpi_last_ack_recv,
+        }
+    }
+}
+
 impl<'a, S> From<&'a S> for MptcpSocketRef<'a, S> {
     fn from(socket: &'a S) -> Self {
         Self(socket)
@@ -135,6 +378,26 @@ unsafe fn getsockopt<T>(fd: RawFd, opt: libc::c_int, val: libc::c_int) -> io::Re
     }
 }
 
+pub(super) unsafe fn setsockopt<T>(
+    fd: RawFd,
+    opt: libc::c_int,
+    val: libc::c_int,
+    payload: &T,
+) -> io::Result<()> {
+    let payload: *const T = payload;
+
+    match libc::setsockopt(
+        fd,
+        opt,
+        val,
+        payload.cast(),
+        size_of::<T>() as libc::socklen_t,
+    ) {
+        -1 => Err(std::io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
+
 pub(crate) fn has_mptcp_info() -> bool {
     match KERNEL_VERSION.as_ref() {
         Some(version) => version.major > 5 || (version.major == 5 && version.minor >= 16),
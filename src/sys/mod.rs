@@ -4,6 +4,12 @@ mod linux;
 #[cfg(target_os = "linux")]
 pub use linux::*;
 
+#[cfg(all(target_os = "linux", any(feature = "tokio", feature = "async-std")))]
+mod netlink;
+
+#[cfg(all(target_os = "linux", any(feature = "tokio", feature = "async-std")))]
+pub use netlink::*;
+
 #[cfg(any(target_os = "macos", target_os = "ios"))]
 mod darwin;
 
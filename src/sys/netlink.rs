@@ -0,0 +1,345 @@
+use std::{
+    io,
+    mem::size_of,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    os::fd::{AsRawFd, RawFd},
+};
+
+use socket2::{Domain, Protocol, Socket, Type};
+
+use crate::event::{MptcpEvent, MptcpEventKind};
+
+const GENL_ID_CTRL: u16 = 0x10;
+const CTRL_CMD_GETFAMILY: u8 = 3;
+const CTRL_ATTR_FAMILY_ID: u16 = 1;
+const CTRL_ATTR_FAMILY_NAME: u16 = 2;
+const CTRL_ATTR_MCAST_GROUPS: u16 = 7;
+const CTRL_ATTR_MCAST_GRP_NAME: u16 = 1;
+const CTRL_ATTR_MCAST_GRP_ID: u16 = 2;
+
+const MPTCP_PM_NAME: &str = "mptcp_pm";
+const MPTCP_PM_EV_GRP_NAME: &str = "mptcp_pm_events";
+
+const MPTCP_ATTR_TOKEN: u16 = 1;
+const MPTCP_ATTR_FAMILY: u16 = 2;
+const MPTCP_ATTR_LOC_ID: u16 = 3;
+const MPTCP_ATTR_REM_ID: u16 = 4;
+const MPTCP_ATTR_SADDR4: u16 = 5;
+const MPTCP_ATTR_SADDR6: u16 = 6;
+const MPTCP_ATTR_DADDR4: u16 = 7;
+const MPTCP_ATTR_DADDR6: u16 = 8;
+const MPTCP_ATTR_SPORT: u16 = 9;
+const MPTCP_ATTR_DPORT: u16 = 10;
+const MPTCP_ATTR_BACKUP: u16 = 11;
+const MPTCP_ATTR_ERROR: u16 = 12;
+
+const NLMSG_HDR_LEN: usize = 16;
+const GENL_HDR_LEN: usize = 4;
+const NLA_HDR_LEN: usize = 4;
+
+fn event_kind(cmd: u8) -> Option<MptcpEventKind> {
+    // See enum mptcp_event_type
+    Some(match cmd {
+        1 => MptcpEventKind::Created,
+        2 => MptcpEventKind::Established,
+        3 => MptcpEventKind::Closed,
+        6 => MptcpEventKind::Announced,
+        7 => MptcpEventKind::Removed,
+        10 => MptcpEventKind::SubflowEstablished,
+        11 => MptcpEventKind::SubflowClosed,
+        13 => MptcpEventKind::SubflowPriority,
+        15 => MptcpEventKind::ListenerCreated,
+        16 => MptcpEventKind::ListenerClosed,
+        _ => return None,
+    })
+}
+
+/// A non-blocking subscription to the events of the in-kernel MPTCP path manager.
+///
+/// Subscribing requires `CAP_NET_ADMIN` on recent kernels.
+#[derive(Debug)]
+pub struct PmEvents {
+    socket: Socket,
+    family: u16,
+}
+
+impl PmEvents {
+    pub fn subscribe() -> io::Result<Self> {
+        let socket = Socket::new(
+            Domain::from(libc::AF_NETLINK),
+            Type::RAW,
+            Some(Protocol::from(libc::NETLINK_GENERIC)),
+        )?;
+
+        let (family, group) = resolve_family(&socket)?;
+        unsafe {
+            super::setsockopt(
+                socket.as_raw_fd(),
+                libc::SOL_NETLINK,
+                libc::NETLINK_ADD_MEMBERSHIP,
+                &group,
+            )?;
+        }
+        socket.set_nonblocking(true)?;
+
+        Ok(Self { socket, family })
+    }
+
+    /// Returns the events received so far, or a `WouldBlock` error if there are none.
+    pub fn recv(&self) -> io::Result<Vec<MptcpEvent>> {
+        let buf = recv(&self.socket)?;
+        let mut events = Vec::new();
+        for (msg_type, payload) in messages(&buf) {
+            if msg_type != self.family || payload.len() < GENL_HDR_LEN {
+                continue;
+            }
+            if let Some(event) = parse_event(payload[0], &payload[GENL_HDR_LEN..]) {
+                events.push(event);
+            }
+        }
+        Ok(events)
+    }
+}
+
+impl AsRawFd for PmEvents {
+    fn as_raw_fd(&self) -> RawFd {
+        self.socket.as_raw_fd()
+    }
+}
+
+fn resolve_family(socket: &Socket) -> io::Result<(u16, u32)> {
+    let mut name = MPTCP_PM_NAME.as_bytes().to_vec();
+    name.push(0);
+
+    let mut msg = Vec::new();
+    push_u32(&mut msg, 0); // length, set below
+    msg.extend_from_slice(&GENL_ID_CTRL.to_ne_bytes());
+    msg.extend_from_slice(&(libc::NLM_F_REQUEST as u16).to_ne_bytes());
+    push_u32(&mut msg, 1); // sequence
+    push_u32(&mut msg, 0); // port id
+    msg.extend_from_slice(&[CTRL_CMD_GETFAMILY, 1, 0, 0]);
+    msg.extend_from_slice(&((NLA_HDR_LEN + name.len()) as u16).to_ne_bytes());
+    msg.extend_from_slice(&CTRL_ATTR_FAMILY_NAME.to_ne_bytes());
+    msg.extend_from_slice(&name);
+    msg.resize(align(msg.len()), 0);
+    let len = msg.len() as u32;
+    msg[..4].copy_from_slice(&len.to_ne_bytes());
+
+    socket.send(&msg)?;
+    let buf = recv(socket)?;
+
+    for (msg_type, payload) in messages(&buf) {
+        if msg_type == libc::NLMSG_ERROR as u16 {
+            let errno = payload
+                .get(..4)
+                .map(|errno| -i32::from_ne_bytes(errno.try_into().unwrap()))
+                .unwrap_or(libc::EINVAL);
+            return Err(io::Error::from_raw_os_error(errno));
+        }
+        if msg_type != GENL_ID_CTRL || payload.len() < GENL_HDR_LEN {
+            continue;
+        }
+
+        let mut family = None;
+        let mut group = None;
+        for (attr, data) in attributes(&payload[GENL_HDR_LEN..]) {
+            match attr {
+                CTRL_ATTR_FAMILY_ID => family = read_u16(data),
+                CTRL_ATTR_MCAST_GROUPS => {
+                    for (_, grp) in attributes(data) {
+                        let mut name = None;
+                        let mut id = None;
+                        for (attr, data) in attributes(grp) {
+                            match attr {
+                                CTRL_ATTR_MCAST_GRP_NAME => name = Some(data),
+                                CTRL_ATTR_MCAST_GRP_ID => id = read_u32(data),
+                                _ => {}
+                            }
+                        }
+                        if name.map(trim_nul) == Some(MPTCP_PM_EV_GRP_NAME.as_bytes()) {
+                            group = id;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if let (Some(family), Some(group)) = (family, group) {
+            return Ok((family, group));
+        }
+    }
+
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        "MPTCP path manager events not available",
+    ))
+}
+
+fn parse_event(cmd: u8, attrs: &[u8]) -> Option<MptcpEvent> {
+    let mut event = MptcpEvent {
+        kind: event_kind(cmd)?,
+        token: None,
+        local_id: None,
+        remote_id: None,
+        local_addr: None,
+        remote_addr: None,
+        backup: None,
+        error: None,
+    };
+
+    let mut saddr = None;
+    let mut daddr = None;
+    let mut sport = None;
+    let mut dport = None;
+
+    for (attr, data) in attributes(attrs) {
+        match attr {
+            MPTCP_ATTR_TOKEN => event.token = read_u32(data),
+            MPTCP_ATTR_FAMILY => {}
+            MPTCP_ATTR_LOC_ID => event.local_id = data.first().copied(),
+            MPTCP_ATTR_REM_ID => event.remote_id = data.first().copied(),
+            MPTCP_ATTR_SADDR4 => saddr = read_ipv4(data),
+            MPTCP_ATTR_SADDR6 => saddr = read_ipv6(data),
+            MPTCP_ATTR_DADDR4 => daddr = read_ipv4(data),
+            MPTCP_ATTR_DADDR6 => daddr = read_ipv6(data),
+            MPTCP_ATTR_SPORT => sport = read_be16(data),
+            MPTCP_ATTR_DPORT => dport = read_be16(data),
+            MPTCP_ATTR_BACKUP => event.backup = data.first().map(|backup| *backup != 0),
+            MPTCP_ATTR_ERROR => event.error = data.first().copied(),
+            _ => {}
+        }
+    }
+
+    event.local_addr = saddr.map(|ip| SocketAddr::new(ip, sport.unwrap_or(0)));
+    event.remote_addr = daddr.map(|ip| SocketAddr::new(ip, dport.unwrap_or(0)));
+    Some(event)
+}
+
+fn recv(socket: &Socket) -> io::Result<Vec<u8>> {
+    let mut buf = vec![0u8; 16384];
+    let len = unsafe { libc::recv(socket.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len(), 0) };
+    if len < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    buf.truncate(len as usize);
+    Ok(buf)
+}
+
+/// Iterates over the netlink messages of `buf`, returning their type and payload.
+fn messages(mut buf: &[u8]) -> impl Iterator<Item = (u16, &[u8])> {
+    std::iter::from_fn(move || {
+        if buf.len() < NLMSG_HDR_LEN {
+            return None;
+        }
+        let len = read_u32(&buf[..4])? as usize;
+        if len < NLMSG_HDR_LEN || len > buf.len() {
+            return None;
+        }
+        let msg_type = read_u16(&buf[4..6])?;
+        let payload = &buf[NLMSG_HDR_LEN..len];
+        buf = &buf[align(len).min(buf.len())..];
+        Some((msg_type, payload))
+    })
+}
+
+/// Iterates over the netlink attributes of `buf`, returning their type and data.
+fn attributes(mut buf: &[u8]) -> impl Iterator<Item = (u16, &[u8])> {
+    std::iter::from_fn(move || {
+        if buf.len() < NLA_HDR_LEN {
+            return None;
+        }
+        let len = read_u16(&buf[..2])? as usize;
+        if len < NLA_HDR_LEN || len > buf.len() {
+            return None;
+        }
+        // Strip the NLA_F_NESTED and NLA_F_NET_BYTEORDER flags
+        let attr = read_u16(&buf[2..4])? & 0x3fff;
+        let data = &buf[NLA_HDR_LEN..len];
+        buf = &buf[align(len).min(buf.len())..];
+        Some((attr, data))
+    })
+}
+
+fn align(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+fn push_u32(buf: &mut Vec<u8>, value: u32) {
+    buf.extend_from_slice(&value.to_ne_bytes());
+}
+
+fn read_u16(data: &[u8]) -> Option<u16> {
+    Some(u16::from_ne_bytes(
+        data.get(..size_of::<u16>())?.try_into().ok()?,
+    ))
+}
+
+fn read_u32(data: &[u8]) -> Option<u32> {
+    Some(u32::from_ne_bytes(
+        data.get(..size_of::<u32>())?.try_into().ok()?,
+    ))
+}
+
+fn read_be16(data: &[u8]) -> Option<u16> {
+    Some(u16::from_be_bytes(
+        data.get(..size_of::<u16>())?.try_into().ok()?,
+    ))
+}
+
+fn read_ipv4(data: &[u8]) -> Option<IpAddr> {
+    let octets: [u8; 4] = data.get(..4)?.try_into().ok()?;
+    Some(Ipv4Addr::from(octets).into())
+}
+
+fn read_ipv6(data: &[u8]) -> Option<IpAddr> {
+    let octets: [u8; 16] = data.get(..16)?.try_into().ok()?;
+    Some(Ipv6Addr::from(octets).into())
+}
+
+fn trim_nul(data: &[u8]) -> &[u8] {
+    data.strip_suffix(&[0]).unwrap_or(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_event() {
+        let mut attrs = Vec::new();
+        for (attr, data) in [
+            (MPTCP_ATTR_TOKEN, 0xdeadbeefu32.to_ne_bytes().to_vec()),
+            (MPTCP_ATTR_SADDR4, vec![10, 0, 0, 1]),
+            (MPTCP_ATTR_SPORT, 1234u16.to_be_bytes().to_vec()),
+            (MPTCP_ATTR_DADDR4, vec![10, 0, 0, 2]),
+            (MPTCP_ATTR_DPORT, 80u16.to_be_bytes().to_vec()),
+            (MPTCP_ATTR_ERROR, vec![104]),
+        ] {
+            attrs.extend_from_slice(&((NLA_HDR_LEN + data.len()) as u16).to_ne_bytes());
+            attrs.extend_from_slice(&attr.to_ne_bytes());
+            attrs.extend_from_slice(&data);
+            attrs.resize(align(attrs.len()), 0);
+        }
+
+        let event = parse_event(11, &attrs).unwrap();
+        assert_eq!(event.kind, MptcpEventKind::SubflowClosed);
+        assert_eq!(event.token, Some(0xdeadbeef));
+        assert_eq!(event.local_addr, Some("10.0.0.1:1234".parse().unwrap()));
+        assert_eq!(event.remote_addr, Some("10.0.0.2:80".parse().unwrap()));
+        assert_eq!(event.error, Some(104));
+
+        assert!(parse_event(42, &attrs).is_none());
+    }
+
+    #[test]
+    fn test_subscribe() {
+        // Requires CAP_NET_ADMIN and a kernel with MPTCP support
+        if let Ok(events) = PmEvents::subscribe() {
+            assert_eq!(
+                events.recv().err().map(|err| err.kind()),
+                Some(io::ErrorKind::WouldBlock)
+            );
+        }
+    }
+}
@@ -5,8 +5,10 @@ use std::{
 
 use socket2::Socket;
 
+use crate::{MptcpInfo, MptcpSubflow};
+
 #[derive(Debug)]
-pub struct MptcpSocketBuilder;
+pub struct MptcpSocketBuilder(Socket);
 
 impl MptcpSocketBuilder {
     pub fn new_v4() -> io::Result<Self> {
@@ -21,19 +23,39 @@ impl MptcpSocketBuilder {
         Err(ErrorKind::Unsupported.into())
     }
 
-    pub fn set_nonblocking(&self) -> io::Result<Self> {
+    pub fn set_nonblocking(self) -> io::Result<Self> {
         Err(ErrorKind::Unsupported.into())
     }
 
+    pub fn socket(&self) -> &Socket {
+        &self.0
+    }
+
+    pub fn into_socket(self) -> Socket {
+        self.0
+    }
+
     pub fn connect(self, _addr: SocketAddr) -> io::Result<Socket> {
         Err(ErrorKind::Unsupported.into())
     }
 
-    pub fn bind(self, _addr: SocketAddr) -> io::Result<Socket> {
+    pub fn bind(self, _addr: SocketAddr, _backlog: i32) -> io::Result<Socket> {
         Err(ErrorKind::Unsupported.into())
     }
 }
 
+pub fn has_mptcp_info() -> bool {
+    false
+}
+
+pub fn set_fast_open_connect(_socket: &Socket) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+pub fn set_fast_open(_socket: &Socket, _queue_len: i32) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
 pub struct MptcpSocketRef<'a, S>(&'a S);
 
 impl<'a, S> MptcpSocketRef<'a, S> {
@@ -44,6 +66,18 @@ impl<'a, S> MptcpSocketRef<'a, S> {
     pub fn has_fallback(&self) -> bool {
         false
     }
+
+    pub fn info(&self) -> io::Result<MptcpInfo> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    pub fn subflows(&self) -> io::Result<Vec<MptcpSubflow>> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    pub fn subflow_bytes(&self) -> io::Result<Vec<u64>> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
 }
 
 impl<'a, S> From<&'a S> for MptcpSocketRef<'a, S> {
@@ -0,0 +1,288 @@
+use std::{
+    future::Future,
+    io,
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "tracing")]
+use tracing::{field::Empty, Instrument, Span};
+
+use crate::{MptcpExt, MptcpStatus};
+#[cfg(feature = "metrics")]
+use crate::{MptcpInfo, MptcpSubflow};
+
+/// Stands for `tracing::Span` when the `tracing` feature is disabled.
+#[cfg(not(feature = "tracing"))]
+struct Span;
+
+/// A connect or bind attempt, reported by the `tracing` and `metrics` features.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Attempt {
+    op: &'static str,
+    protocol: &'static str,
+    fallback: bool,
+}
+
+impl Attempt {
+    /// An attempt using MPTCP.
+    pub(crate) const fn mptcp(op: &'static str) -> Self {
+        Self {
+            op,
+            protocol: "mptcp",
+            fallback: false,
+        }
+    }
+
+    /// An attempt using TCP, not because MPTCP failed.
+    pub(crate) const fn tcp(op: &'static str) -> Self {
+        Self {
+            op,
+            protocol: "tcp",
+            fallback: false,
+        }
+    }
+
+    /// An attempt using TCP after MPTCP failed, or is known to fail for the address.
+    pub(crate) const fn fallback(op: &'static str) -> Self {
+        Self {
+            op,
+            protocol: "tcp",
+            fallback: true,
+        }
+    }
+
+    /// Runs the resolution of the addresses to try.
+    pub(crate) fn resolve<T>(&self, f: impl FnOnce() -> io::Result<T>) -> io::Result<T> {
+        let span = self.resolve_span();
+        let start = Instant::now();
+        let res = in_scope(&span, f);
+        record_span(&span, start.elapsed(), &res);
+        res
+    }
+
+    /// Runs the asynchronous resolution of the addresses to try.
+    pub(crate) async fn resolve_async<T>(
+        &self,
+        fut: impl Future<Output = io::Result<T>>,
+    ) -> io::Result<T> {
+        let span = self.resolve_span();
+        let start = Instant::now();
+        let res = instrument(fut, &span).await;
+        record_span(&span, start.elapsed(), &res);
+        res
+    }
+
+    /// Runs the attempt with one of the resolved addresses.
+    pub(crate) fn run<T>(
+        &self,
+        addr: SocketAddr,
+        f: impl FnOnce(SocketAddr) -> io::Result<T>,
+    ) -> io::Result<T> {
+        let span = self.span(addr);
+        let start = Instant::now();
+        let res = in_scope(&span, || f(addr));
+        self.record(&span, start.elapsed(), &res);
+        res
+    }
+
+    /// Runs the asynchronous attempt with one of the resolved addresses.
+    pub(crate) async fn run_async<T>(
+        &self,
+        addr: SocketAddr,
+        fut: impl Future<Output = io::Result<T>>,
+    ) -> io::Result<T> {
+        let span = self.span(addr);
+        let start = Instant::now();
+        let res = instrument(fut, &span).await;
+        self.record(&span, start.elapsed(), &res);
+        res
+    }
+
+    #[cfg(feature = "tracing")]
+    fn resolve_span(&self) -> Span {
+        tracing::debug_span!(
+            "mptcp.resolve",
+            op = self.op,
+            protocol = self.protocol,
+            fallback = self.fallback,
+            errno = Empty,
+            error = Empty,
+            elapsed_us = Empty,
+        )
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    fn resolve_span(&self) -> Span {
+        Span
+    }
+
+    #[cfg(feature = "tracing")]
+    fn span(&self, addr: SocketAddr) -> Span {
+        tracing::debug_span!(
+            "mptcp.attempt",
+            op = self.op,
+            protocol = self.protocol,
+            fallback = self.fallback,
+            %addr,
+            reason = Empty,
+            errno = Empty,
+            error = Empty,
+            elapsed_us = Empty,
+        )
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    fn span(&self, _addr: SocketAddr) -> Span {
+        Span
+    }
+
+    fn record<T>(&self, span: &Span, elapsed: Duration, res: &io::Result<T>) {
+        record_span(span, elapsed, res);
+
+        #[cfg(feature = "metrics")]
+        {
+            let fallback = if self.fallback { "true" } else { "false" };
+            let result = if res.is_ok() { "ok" } else { "error" };
+            metrics::counter!(
+                "mptcp_attempts_total",
+                "op" => self.op,
+                "protocol" => self.protocol,
+                "fallback" => fallback,
+                "result" => result,
+            )
+            .increment(1);
+            metrics::histogram!(
+                "mptcp_attempt_duration_seconds",
+                "op" => self.op,
+                "protocol" => self.protocol,
+            )
+            .record(elapsed);
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+fn in_scope<T>(span: &Span, f: impl FnOnce() -> T) -> T {
+    span.in_scope(f)
+}
+
+#[cfg(not(feature = "tracing"))]
+fn in_scope<T>(_span: &Span, f: impl FnOnce() -> T) -> T {
+    f()
+}
+
+#[cfg(feature = "tracing")]
+async fn instrument<F: Future>(fut: F, span: &Span) -> F::Output {
+    fut.instrument(span.clone()).await
+}
+
+#[cfg(not(feature = "tracing"))]
+async fn instrument<F: Future>(fut: F, _span: &Span) -> F::Output {
+    fut.await
+}
+
+#[cfg(feature = "tracing")]
+fn record_span<T>(span: &Span, elapsed: Duration, res: &io::Result<T>) {
+    span.record("elapsed_us", elapsed.as_micros() as u64);
+    if let Err(err) = res {
+        if let Some(errno) = err.raw_os_error() {
+            span.record("errno", errno);
+        }
+        span.record("error", tracing::field::display(err));
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+fn record_span<T>(_span: &Span, _elapsed: Duration, _res: &io::Result<T>) {}
+
+/// The reason of a fallback to TCP.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Fallback<'a> {
+    /// The MPTCP attempt failed.
+    Error(&'a io::Error),
+    /// MPTCP previously failed for the destination, see `FallbackCache`.
+    Cached,
+    /// The TCP attempt of a race completed first.
+    Race,
+    /// The MPTCP connection was established, but the peer made it fall back to TCP.
+    Peer,
+}
+
+impl Fallback<'_> {
+    pub(crate) fn reason(&self) -> &'static str {
+        match self {
+            Fallback::Error(_) => "error",
+            Fallback::Cached => "cached",
+            Fallback::Race => "race",
+            Fallback::Peer => "peer",
+        }
+    }
+}
+
+/// Reports that a connect or bind operation falls back to TCP.
+pub(crate) fn fallback(op: &'static str, fallback: Fallback<'_>) {
+    #[cfg(feature = "tracing")]
+    match fallback {
+        Fallback::Error(err) => tracing::debug!(
+            op,
+            reason = fallback.reason(),
+            errno = err.raw_os_error(),
+            error = %err,
+            "falling back to TCP"
+        ),
+        _ => tracing::debug!(op, reason = fallback.reason(), "falling back to TCP"),
+    }
+
+    #[cfg(feature = "metrics")]
+    metrics::counter!("mptcp_fallbacks_total", "op" => op, "reason" => fallback.reason())
+        .increment(1);
+
+    #[cfg(not(any(feature = "tracing", feature = "metrics")))]
+    let _ = (op, fallback);
+}
+
+/// Reports a connection established with MPTCP, which falls back to TCP if the peer
+/// does not support MPTCP.
+pub(crate) fn connected(sock: &impl MptcpExt) {
+    // Without MPTCP_INFO, every MPTCP connection looks like it fell back
+    #[cfg(any(feature = "tracing", feature = "metrics"))]
+    if crate::sys::has_mptcp_info()
+        && sock.mptcp_status() == (MptcpStatus::Mptcp { has_fallback: true })
+    {
+        // Connections are reported in the scope of their attempt
+        #[cfg(feature = "tracing")]
+        Span::current().record("reason", Fallback::Peer.reason());
+        fallback("connect", Fallback::Peer);
+    }
+
+    #[cfg(not(any(feature = "tracing", feature = "metrics")))]
+    let _ = sock;
+}
+
+/// Reports the status of an accepted connection.
+pub(crate) fn accepted(status: MptcpStatus) {
+    #[cfg(feature = "metrics")]
+    {
+        let protocol = if status.is_mptcp() { "mptcp" } else { "tcp" };
+        metrics::counter!("mptcp_accepted_total", "protocol" => protocol).increment(1);
+    }
+
+    #[cfg(not(feature = "metrics"))]
+    let _ = status;
+}
+
+/// Reports the subflows of a connection, with the bytes sent and received by each.
+#[cfg(feature = "metrics")]
+pub(crate) fn subflows(info: &MptcpInfo, subflows: &[(MptcpSubflow, u64)]) {
+    metrics::histogram!("mptcp_subflows").record(info.subflows as f64 + 1.0);
+
+    for (subflow, bytes) in subflows {
+        metrics::histogram!(
+            "mptcp_subflow_bytes",
+            "local_addr" => subflow.local_addr.to_string(),
+            "remote_addr" => subflow.remote_addr.to_string(),
+        )
+        .record(*bytes as f64);
+    }
+}
@@ -1,15 +1,39 @@
+#[cfg(not(target_os = "linux"))]
+use std::convert::Infallible;
 use std::{
     future::{poll_fn, Future},
-    io,
+    io::{self, IoSlice},
     net::SocketAddr,
+    os::fd::{AsFd, AsRawFd, BorrowedFd, RawFd},
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
 };
 
-use tokio::net::{lookup_host, TcpListener, TcpStream, ToSocketAddrs};
+use futures_core::Stream;
+use socket2::Socket;
+#[cfg(target_os = "linux")]
+use tokio::io::unix::AsyncFd;
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    net::{
+        lookup_host,
+        tcp::{OwnedReadHalf, OwnedWriteHalf},
+        TcpListener, TcpSocket, TcpStream, ToSocketAddrs,
+    },
+    time::{sleep, timeout, timeout_at, Instant},
+};
 
-use crate::{sys::MptcpSocketBuilder, MptcpExt, MptcpOpt, MptcpSocket};
+use crate::{
+    event::{self, PmUpdate, POLL_INTERVAL},
+    race::race,
+    sys,
+    telemetry::{self, Attempt, Fallback},
+    AcceptFilter, ConnectOptions, FallbackCache, ListenOptions, MptcpExt, MptcpInfo, MptcpOpt,
+    MptcpSocket, MptcpSocketBuilder, MptcpStatus, MptcpSubflow,
+};
 
 /// Extension trait for tokio::net::TcpStream to support MPTCP.
-#[async_trait::async_trait(?Send)]
 pub trait MptcpStreamExt {
     type Output;
 
@@ -24,10 +48,12 @@ pub trait MptcpStreamExt {
     ///
     /// Returns an `io::Result` containing the MptcpSocket if the connection is successful,
     /// or an `io::Error` if an error occurs during the connection.
-    async fn connect_mptcp_opt<A: ToSocketAddrs>(
+    fn connect_mptcp_opt<A>(
         addr: A,
         opt: MptcpOpt,
-    ) -> io::Result<MptcpSocket<Self::Output>>;
+    ) -> impl Future<Output = io::Result<MptcpSocket<Self::Output>>> + Send
+    where
+        A: ToSocketAddrs + Send + Sync;
 
     /// Establishes an MPTCP connection with the given address. If MPTCP cannot be used
     /// the connection will fallback to a regular TCP connection.
@@ -40,8 +66,13 @@ pub trait MptcpStreamExt {
     ///
     /// Returns an `io::Result` containing the MptcpSocket if the connection is successful,
     /// or an `io::Error` if an error occurs during the connection.
-    async fn connect_mptcp<A: ToSocketAddrs>(addr: A) -> io::Result<MptcpSocket<Self::Output>> {
-        Self::connect_mptcp_opt(addr, MptcpOpt::Fallback).await
+    fn connect_mptcp<A>(
+        addr: A,
+    ) -> impl Future<Output = io::Result<MptcpSocket<Self::Output>>> + Send
+    where
+        A: ToSocketAddrs + Send + Sync,
+    {
+        Self::connect_mptcp_opt(addr, MptcpOpt::Fallback)
     }
 
     /// Establishes an MPTCP connection with the given address. Returns an error even if
@@ -55,15 +86,134 @@ pub trait MptcpStreamExt {
     ///
     /// Returns an `io::Result` containing the MptcpSocket if the connection is successful,
     /// or an `io::Error` if an error occurs during the connection.
-    async fn connect_mptcp_force<A: ToSocketAddrs>(addr: A) -> io::Result<Self::Output> {
-        Ok(Self::connect_mptcp_opt(addr, MptcpOpt::NoFallback)
-            .await?
-            .into_socket())
+    fn connect_mptcp_force<A>(addr: A) -> impl Future<Output = io::Result<Self::Output>> + Send
+    where
+        A: ToSocketAddrs + Send + Sync,
+    {
+        async move {
+            Ok(Self::connect_mptcp_opt(addr, MptcpOpt::NoFallback)
+                .await?
+                .into_socket())
+        }
     }
+
+    /// Races an MPTCP connection against a regular TCP connection to the given address.
+    ///
+    /// The MPTCP attempt is started first. The TCP attempt is started after `delay`,
+    /// or as soon as the MPTCP attempt fails. The first connection to be established
+    /// is returned and the other attempt is cancelled. This protects against paths
+    /// that silently drop SYNs carrying MPTCP options.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address to connect to.
+    /// * `delay` - The delay before starting the TCP attempt.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing `MptcpSocket::Mptcp` if the MPTCP attempt won,
+    /// `MptcpSocket::Tcp` if the TCP attempt won, or an `io::Error` if both failed.
+    fn connect_mptcp_race<A>(
+        addr: A,
+        delay: Duration,
+    ) -> impl Future<Output = io::Result<MptcpSocket<Self::Output>>> + Send
+    where
+        A: ToSocketAddrs + Send + Sync;
+
+    /// Establishes a connection with the given address, using `cache` to skip MPTCP
+    /// for destinations known to fall back to TCP.
+    ///
+    /// Destinations found in the cache are connected to with plain TCP directly. Otherwise
+    /// an MPTCP connection is attempted, falling back to TCP if MPTCP cannot be used, and
+    /// the destination is added to the cache if the established connection reports
+    /// a fallback to TCP.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address to connect to.
+    /// * `cache` - The cache of destinations falling back to TCP.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing the MptcpSocket if the connection is successful,
+    /// or an `io::Error` if an error occurs during the connection.
+    fn connect_mptcp_cached<A>(
+        addr: A,
+        cache: &FallbackCache,
+    ) -> impl Future<Output = io::Result<MptcpSocket<Self::Output>>> + Send
+    where
+        A: ToSocketAddrs + Send + Sync;
+
+    /// Establishes an MPTCP connection with the given address and MptcpOpt, starting the
+    /// initial subflow from the `local` address. The TCP connection used in case of
+    /// fallback is bound to the same address.
+    ///
+    /// # Arguments
+    ///
+    /// * `local` - The local address to bind to before connecting.
+    /// * `addr` - The address to connect to.
+    /// * `opt` - The MptcpOpt options for the connection.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing the MptcpSocket if the connection is successful,
+    /// or an `io::Error` if an error occurs during the connection.
+    fn connect_mptcp_from<A>(
+        local: SocketAddr,
+        addr: A,
+        opt: MptcpOpt,
+    ) -> impl Future<Output = io::Result<MptcpSocket<Self::Output>>> + Send
+    where
+        A: ToSocketAddrs + Send + Sync;
+
+    /// Establishes an MPTCP connection with the given address and MptcpOpt, starting the
+    /// initial subflow on the given network interface using `SO_BINDTODEVICE`. The TCP
+    /// connection used in case of fallback is bound to the same interface.
+    ///
+    /// Only supported on Linux.
+    ///
+    /// # Arguments
+    ///
+    /// * `interface` - The name of the network interface to use.
+    /// * `addr` - The address to connect to.
+    /// * `opt` - The MptcpOpt options for the connection.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing the MptcpSocket if the connection is successful,
+    /// or an `io::Error` if an error occurs during the connection.
+    fn connect_mptcp_from_device<A>(
+        interface: &str,
+        addr: A,
+        opt: MptcpOpt,
+    ) -> impl Future<Output = io::Result<MptcpSocket<Self::Output>>> + Send
+    where
+        A: ToSocketAddrs + Send + Sync;
+
+    /// Establishes an MPTCP connection with the given address and ConnectOptions. If
+    /// MPTCP cannot be used the connection will fallback to a regular TCP connection,
+    /// using the same options.
+    ///
+    /// Use `MptcpSocketBuilder` to configure a connection that does not fall back to TCP.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address to connect to.
+    /// * `options` - The options of the connection.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing the MptcpSocket if the connection is successful,
+    /// or an `io::Error` if an error occurs during the connection.
+    fn connect_mptcp_with<A>(
+        addr: A,
+        options: ConnectOptions,
+    ) -> impl Future<Output = io::Result<MptcpSocket<Self::Output>>> + Send
+    where
+        A: ToSocketAddrs + Send + Sync;
 }
 
 /// Extension trait for tokio::net::TcpListener.
-#[async_trait::async_trait(?Send)]
 pub trait MptcpListenerExt {
     type Output;
 
@@ -77,10 +227,12 @@ pub trait MptcpListenerExt {
     /// # Returns
     ///
     /// Returns an `io::Result` containing the MptcpSocket with the specified MptcpOpt.
-    async fn bind_mptcp_opt<A: ToSocketAddrs>(
+    fn bind_mptcp_opt<A>(
         addr: A,
         opt: MptcpOpt,
-    ) -> io::Result<MptcpSocket<Self::Output>>;
+    ) -> impl Future<Output = io::Result<MptcpSocket<Self::Output>>> + Send
+    where
+        A: ToSocketAddrs + Send + Sync;
 
     /// Binds an MPTCP socket to the specified address. If MPTCP cannot be used
     /// the connection will fallback to a regular TCP connection.
@@ -92,8 +244,11 @@ pub trait MptcpListenerExt {
     /// # Returns
     ///
     /// Returns an `io::Result` containing the MptcpSocket with the default MptcpOpt (Fallback).
-    async fn bind_mptcp<A: ToSocketAddrs>(addr: A) -> io::Result<MptcpSocket<Self::Output>> {
-        Self::bind_mptcp_opt(addr, MptcpOpt::Fallback).await
+    fn bind_mptcp<A>(addr: A) -> impl Future<Output = io::Result<MptcpSocket<Self::Output>>> + Send
+    where
+        A: ToSocketAddrs + Send + Sync,
+    {
+        Self::bind_mptcp_opt(addr, MptcpOpt::Fallback)
     }
 
     /// Binds an MPTCP socket to the specified address. Returns an error even if
@@ -106,19 +261,264 @@ pub trait MptcpListenerExt {
     /// # Returns
     ///
     /// Returns an `io::Result` containing the MptcpSocket with the MptcpOpt set to NoFallback.
-    async fn bind_mptcp_force<A: ToSocketAddrs>(addr: A) -> io::Result<Self::Output> {
-        Ok(Self::bind_mptcp_opt(addr, MptcpOpt::NoFallback)
-            .await?
-            .into_socket())
+    fn bind_mptcp_force<A>(addr: A) -> impl Future<Output = io::Result<Self::Output>> + Send
+    where
+        A: ToSocketAddrs + Send + Sync,
+    {
+        async move {
+            Ok(Self::bind_mptcp_opt(addr, MptcpOpt::NoFallback)
+                .await?
+                .into_socket())
+        }
+    }
+
+    /// Binds an MPTCP socket to the specified address with the given ListenOptions. If
+    /// MPTCP cannot be used the listener will fallback to a regular TCP listener, using
+    /// the same options.
+    ///
+    /// Use `MptcpSocketBuilder` to configure a listener that does not fall back to TCP.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address to bind the socket to.
+    /// * `options` - The options of the listener.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing the MptcpSocket with the default MptcpOpt (Fallback).
+    fn bind_mptcp_with<A>(
+        addr: A,
+        options: ListenOptions,
+    ) -> impl Future<Output = io::Result<MptcpSocket<Self::Output>>> + Send
+    where
+        A: ToSocketAddrs + Send + Sync;
+
+    /// Binds `shards` MPTCP sockets to the specified address with SO_REUSEPORT, so that
+    /// each listener can be served by its own worker thread. If MPTCP cannot be used
+    /// the listeners will fallback to regular TCP listeners.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address to bind the sockets to.
+    /// * `shards` - The number of listeners to create.
+    /// * `opt` - The MptcpOpt to use for the sockets.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing the listeners, which all use MPTCP or all use TCP.
+    fn bind_mptcp_sharded<A>(
+        addr: A,
+        shards: usize,
+        opt: MptcpOpt,
+    ) -> impl Future<Output = io::Result<Vec<MptcpSocket<Self::Output>>>> + Send
+    where
+        A: ToSocketAddrs + Send + Sync;
+
+    /// Accepts a new incoming connection from this listener.
+    ///
+    /// The connection is returned as `MptcpSocket::Mptcp` only if the peer negotiated
+    /// MPTCP, and as `MptcpSocket::Tcp` otherwise, including when the listener itself
+    /// is a TCP listener. Fallbacks can only be detected on Linux >= 5.16.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing the connection and the address of the peer.
+    fn accept_mptcp(
+        &self,
+    ) -> impl Future<Output = io::Result<(MptcpSocket<TcpStream>, SocketAddr)>> + Send;
+
+    /// Accepts a new incoming connection using MPTCP from this listener.
+    ///
+    /// Connections that do not use MPTCP are rejected by `filter`, and this method keeps
+    /// waiting for the next connection.
+    /// Returns `io::ErrorKind::Unsupported` on Linux < 5.16, where fallbacks cannot
+    /// be detected.
+    ///
+    /// # Arguments
+    ///
+    /// * `filter` - The AcceptFilter handling rejected connections.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing the connection and the address of the peer.
+    fn accept_mptcp_filtered(
+        &self,
+        filter: &AcceptFilter<TcpStream>,
+    ) -> impl Future<Output = io::Result<(TcpStream, SocketAddr)>> + Send;
+
+    /// Returns a stream over the connections received on this listener, as returned
+    /// by `accept_mptcp`. The stream never returns `None`.
+    fn incoming_mptcp(&self) -> IncomingMptcp<'_>;
+}
+
+/// Extension trait for MptcpSocketBuilder to create tokio::net sockets.
+pub trait MptcpSocketBuilderExt {
+    /// Establishes an MPTCP connection with the given address, using the options of
+    /// the builder.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address to connect to.
+    /// * `opt` - The MptcpOpt options for the connection.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing the MptcpSocket if the connection is successful,
+    /// or an `io::Error` if an error occurs during the connection.
+    fn connect<A>(
+        &self,
+        addr: A,
+        opt: MptcpOpt,
+    ) -> impl Future<Output = io::Result<MptcpSocket<TcpStream>>> + Send
+    where
+        A: ToSocketAddrs + Send + Sync;
+
+    /// Binds an MPTCP socket to the specified address, using the options of the builder.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address to bind the socket to.
+    /// * `opt` - The MptcpOpt to use for the socket.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing the MptcpSocket with the specified MptcpOpt.
+    fn bind<A>(
+        &self,
+        addr: A,
+        opt: MptcpOpt,
+    ) -> impl Future<Output = io::Result<MptcpSocket<TcpListener>>> + Send
+    where
+        A: ToSocketAddrs + Send + Sync;
+
+    /// Binds `shards` MPTCP sockets to the specified address with SO_REUSEPORT, using the
+    /// options of the builder.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address to bind the sockets to.
+    /// * `shards` - The number of listeners to create.
+    /// * `opt` - The MptcpOpt to use for the sockets.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing the listeners, which all use MPTCP or all use TCP.
+    fn bind_sharded<A>(
+        &self,
+        addr: A,
+        shards: usize,
+        opt: MptcpOpt,
+    ) -> impl Future<Output = io::Result<Vec<MptcpSocket<TcpListener>>>> + Send
+    where
+        A: ToSocketAddrs + Send + Sync;
+}
+
+/// Extension trait for tokio::net::TcpSocket to create MPTCP sockets.
+///
+/// The created sockets can be configured with the `TcpSocket` API before calling
+/// `connect` or `listen`.
+pub trait MptcpTcpSocketExt: Sized {
+    /// Creates a new IPv4 MPTCP socket. Returns an error if MPTCP cannot be used.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing the socket, or an `io::Error` if the socket
+    /// cannot be created.
+    fn new_mptcp_v4() -> io::Result<Self>;
+
+    /// Creates a new IPv6 MPTCP socket. Returns an error if MPTCP cannot be used.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing the socket, or an `io::Error` if the socket
+    /// cannot be created.
+    fn new_mptcp_v6() -> io::Result<Self>;
+
+    /// Creates a new MPTCP socket for the address family of `addr`. Returns an error if
+    /// MPTCP cannot be used.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address the socket will connect or bind to.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing the socket, or an `io::Error` if the socket
+    /// cannot be created.
+    fn new_mptcp_for_addr(addr: SocketAddr) -> io::Result<Self>;
+
+    /// Creates a new MPTCP socket for the address family of `addr` with the given
+    /// MptcpOpt. If MPTCP cannot be used and `opt` is `MptcpOpt::Fallback`, a TCP
+    /// socket is created instead.
+    ///
+    /// The fallback only happens when creating the socket: a connection made with an
+    /// MPTCP socket falls back to TCP in the kernel if the peer does not support MPTCP.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The address the socket will connect or bind to.
+    /// * `opt` - The MptcpOpt to use for the socket.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing the MptcpSocket, or an `io::Error` if the
+    /// socket cannot be created.
+    fn new_mptcp_for_addr_opt(addr: SocketAddr, opt: MptcpOpt) -> io::Result<MptcpSocket<Self>>;
+}
+
+fn new_mptcp_socket(builder: io::Result<sys::MptcpSocketBuilder>) -> io::Result<TcpSocket> {
+    let socket = builder?.set_nonblocking()?.into_socket();
+    Ok(TcpSocket::from_std_stream(socket.into()))
+}
+
+impl MptcpTcpSocketExt for TcpSocket {
+    fn new_mptcp_v4() -> io::Result<Self> {
+        new_mptcp_socket(sys::MptcpSocketBuilder::new_v4())
+    }
+
+    fn new_mptcp_v6() -> io::Result<Self> {
+        new_mptcp_socket(sys::MptcpSocketBuilder::new_v6())
+    }
+
+    fn new_mptcp_for_addr(addr: SocketAddr) -> io::Result<Self> {
+        new_mptcp_socket(sys::MptcpSocketBuilder::new_for_addr(addr))
+    }
+
+    fn new_mptcp_for_addr_opt(addr: SocketAddr, opt: MptcpOpt) -> io::Result<MptcpSocket<Self>> {
+        match Self::new_mptcp_for_addr(addr) {
+            Ok(sock) => Ok(MptcpSocket::Mptcp(sock)),
+            Err(err) if matches!(opt, MptcpOpt::Fallback) => {
+                telemetry::fallback("socket", Fallback::Error(&err));
+                Ok(MptcpSocket::Tcp(if addr.is_ipv4() {
+                    Self::new_v4()?
+                } else {
+                    Self::new_v6()?
+                }))
+            }
+            Err(err) => Err(err),
+        }
     }
 }
 
-async fn resolve_each_addr<A: ToSocketAddrs, F, Fut, T>(addr: &A, mut f: F) -> io::Result<T>
+async fn resolve_each_addr<A: ToSocketAddrs, F, Fut, T>(
+    addr: &A,
+    attempt: Attempt,
+    mut f: F,
+) -> io::Result<T>
+where
+    F: FnMut(SocketAddr) -> Fut,
+    Fut: Future<Output = io::Result<T>>,
+{
+    let addrs = attempt.resolve_async(lookup_host(addr)).await?;
+    each_addr(addrs, |addr| attempt.run_async(addr, f(addr))).await
+}
+
+async fn each_addr<I, F, Fut, T>(addrs: I, mut f: F) -> io::Result<T>
 where
+    I: IntoIterator<Item = SocketAddr>,
     F: FnMut(SocketAddr) -> Fut,
     Fut: Future<Output = io::Result<T>>,
 {
-    let addrs = lookup_host(addr).await?;
     let mut last_err = None;
     for addr in addrs {
         match f(addr).await {
@@ -134,110 +534,829 @@ where
     }))
 }
 
-#[async_trait::async_trait(?Send)]
-impl MptcpStreamExt for TcpStream {
-    type Output = Self;
+async fn connect_mptcp_addr(
+    builder: &MptcpSocketBuilder,
+    addr: SocketAddr,
+) -> io::Result<TcpStream> {
+    let sock = wait_connected(builder.connect_mptcp(addr, true)?).await?;
+    telemetry::connected(&sock);
+    Ok(sock)
+}
 
-    async fn connect_mptcp_opt<A: ToSocketAddrs>(
-        addr: A,
-        opt: MptcpOpt,
-    ) -> io::Result<MptcpSocket<Self::Output>> {
-        match resolve_each_addr(&addr, |addr| async move {
-            let sock = MptcpSocketBuilder::new_for_addr(addr)?
-                .set_nonblocking()?
-                .connect(addr)
-                .and_then(|sock| TcpStream::from_std(sock.into()))?;
-            // Wait for the socket to be writable
-            poll_fn(|cx| sock.poll_write_ready(cx)).await?;
-            Ok(sock)
+async fn wait_connected(sock: Socket) -> io::Result<TcpStream> {
+    let sock = TcpStream::from_std(sock.into())?;
+    // Wait for the socket to be writable
+    poll_fn(|cx| sock.poll_write_ready(cx)).await?;
+    // A failed connection is also reported as writable
+    match sock.take_error()? {
+        Some(err) => Err(err),
+        None => Ok(sock),
+    }
+}
+
+impl MptcpSocketBuilderExt for MptcpSocketBuilder {
+    async fn connect<A>(&self, addr: A, opt: MptcpOpt) -> io::Result<MptcpSocket<TcpStream>>
+    where
+        A: ToSocketAddrs + Send + Sync,
+    {
+        match resolve_each_addr(&addr, Attempt::mptcp("connect"), |addr| {
+            connect_mptcp_addr(self, addr)
         })
         .await
         {
             Ok(sock) => Ok(MptcpSocket::Mptcp(sock)),
-            Err(_) if matches!(opt, MptcpOpt::Fallback) => {
-                Ok(MptcpSocket::Tcp(Self::connect(addr).await?))
+            Err(err) if matches!(opt, MptcpOpt::Fallback) => {
+                telemetry::fallback("connect", Fallback::Error(&err));
+                Ok(MptcpSocket::Tcp(
+                    resolve_each_addr(&addr, Attempt::fallback("connect"), |addr| async move {
+                        wait_connected(self.connect_tcp(addr, true)?).await
+                    })
+                    .await?,
+                ))
             }
             Err(err) => Err(err),
         }
     }
-}
-
-impl MptcpExt for TcpStream {}
 
-impl From<MptcpSocket<TcpStream>> for TcpStream {
-    fn from(socket: MptcpSocket<TcpStream>) -> Self {
-        socket.into_socket()
+    async fn bind<A>(&self, addr: A, opt: MptcpOpt) -> io::Result<MptcpSocket<TcpListener>>
+    where
+        A: ToSocketAddrs + Send + Sync,
+    {
+        match resolve_each_addr(&addr, Attempt::mptcp("bind"), |addr| async move {
+            TcpListener::from_std(self.bind_mptcp(addr, true)?.into())
+        })
+        .await
+        {
+            Ok(sock) => Ok(MptcpSocket::Mptcp(sock)),
+            Err(err) if matches!(opt, MptcpOpt::Fallback) => {
+                telemetry::fallback("bind", Fallback::Error(&err));
+                Ok(MptcpSocket::Tcp(
+                    resolve_each_addr(&addr, Attempt::fallback("bind"), |addr| async move {
+                        TcpListener::from_std(self.bind_tcp(addr, true)?.into())
+                    })
+                    .await?,
+                ))
+            }
+            Err(err) => Err(err),
+        }
     }
-}
-
-#[async_trait::async_trait(?Send)]
-impl MptcpListenerExt for TcpListener {
-    type Output = Self;
 
-    async fn bind_mptcp_opt<A: ToSocketAddrs>(
+    async fn bind_sharded<A>(
+        &self,
         addr: A,
+        shards: usize,
         opt: MptcpOpt,
-    ) -> io::Result<MptcpSocket<Self::Output>> {
-        match resolve_each_addr(&addr, |addr| async move {
-            MptcpSocketBuilder::new_for_addr(addr)?
-                .set_nonblocking()?
-                .bind(addr)
-                .and_then(|sock| TcpListener::from_std(sock.into()))
+    ) -> io::Result<Vec<MptcpSocket<TcpListener>>>
+    where
+        A: ToSocketAddrs + Send + Sync,
+    {
+        match resolve_each_addr(&addr, Attempt::mptcp("bind"), |addr| async move {
+            self.bind_shards(addr, shards, |builder, addr| builder.bind_mptcp(addr, true))
         })
         .await
         {
-            Ok(sock) => Ok(MptcpSocket::Mptcp(sock)),
-            Err(_) if matches!(opt, MptcpOpt::Fallback) => {
-                Ok(MptcpSocket::Tcp(Self::bind(addr).await?))
+            Ok(socks) => socks
+                .into_iter()
+                .map(|sock| TcpListener::from_std(sock.into()).map(MptcpSocket::Mptcp))
+                .collect(),
+            Err(err) if matches!(opt, MptcpOpt::Fallback) => {
+                telemetry::fallback("bind", Fallback::Error(&err));
+
+                resolve_each_addr(&addr, Attempt::fallback("bind"), |addr| async move {
+                    self.bind_shards(addr, shards, |builder, addr| builder.bind_tcp(addr, true))
+                })
+                .await?
+                .into_iter()
+                .map(|sock| TcpListener::from_std(sock.into()).map(MptcpSocket::Tcp))
+                .collect()
             }
             Err(err) => Err(err),
         }
     }
 }
 
-impl From<MptcpSocket<TcpListener>> for TcpListener {
-    fn from(socket: MptcpSocket<TcpListener>) -> Self {
-        socket.into_socket()
-    }
-}
-
-#[cfg(all(test, target_os = "linux"))]
-mod tests {
-    use super::*;
+impl MptcpStreamExt for TcpStream {
+    type Output = Self;
 
-    use std::net::{IpAddr, Ipv4Addr};
+    async fn connect_mptcp_opt<A>(addr: A, opt: MptcpOpt) -> io::Result<MptcpSocket<Self::Output>>
+    where
+        A: ToSocketAddrs + Send + Sync,
+    {
+        MptcpSocketBuilder::new().connect(addr, opt).await
+    }
 
-    use crate::sys::is_mptcp_enabled;
+    async fn connect_mptcp_race<A>(
+        addr: A,
+        delay: Duration,
+    ) -> io::Result<MptcpSocket<Self::Output>>
+    where
+        A: ToSocketAddrs + Send + Sync,
+    {
+        // Resolve once, so that both attempts use the same addresses
+        let (attempt, tcp) = (Attempt::mptcp("connect"), Attempt::tcp("connect"));
+        let builder = &MptcpSocketBuilder::new();
+        let addrs: Vec<_> = attempt.resolve_async(lookup_host(addr)).await?.collect();
+        race(
+            each_addr(addrs.iter().copied(), |addr| {
+                attempt.run_async(addr, connect_mptcp_addr(builder, addr))
+            }),
+            sleep(delay),
+            each_addr(addrs.iter().copied(), |addr| {
+                tcp.run_async(addr, Self::connect(addr))
+            }),
+        )
+        .await
+    }
 
-    #[tokio::test]
-    async fn test_resolve_each_addr() {
-        let addr = "127.0.0.1:80";
-        let result = resolve_each_addr(&addr, |addr| async move {
-            assert_eq!(addr.port(), 80);
-            assert_eq!(addr.ip(), IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)));
-            Ok(())
+    async fn connect_mptcp_cached<A>(
+        addr: A,
+        cache: &FallbackCache,
+    ) -> io::Result<MptcpSocket<Self::Output>>
+    where
+        A: ToSocketAddrs + Send + Sync,
+    {
+        let addrs = Attempt::mptcp("connect")
+            .resolve_async(lookup_host(addr))
+            .await?;
+        each_addr(addrs, |addr| async move {
+            if cache.contains(&addr) {
+                telemetry::fallback("connect", Fallback::Cached);
+            } else {
+                let attempt = Attempt::mptcp("connect");
+                match attempt
+                    .run_async(addr, connect_mptcp_addr(&MptcpSocketBuilder::new(), addr))
+                    .await
+                {
+                    Ok(sock) => {
+                        cache.record(addr, &sock);
+                        return Ok(MptcpSocket::Mptcp(sock));
+                    }
+                    Err(err) => telemetry::fallback("connect", Fallback::Error(&err)),
+                }
+            }
+            Attempt::fallback("connect")
+                .run_async(addr, Self::connect(addr))
+                .await
+                .map(MptcpSocket::Tcp)
         })
-        .await;
-        assert!(result.is_ok());
+        .await
     }
 
-    #[tokio::test]
-    async fn test_resolve_each_addr_error() {
-        let addr = "thisisanerror";
-        let result = resolve_each_addr(&addr, |_| async { Ok(()) }).await;
-        assert!(result.is_err());
+    async fn connect_mptcp_from<A>(
+        local: SocketAddr,
+        addr: A,
+        opt: MptcpOpt,
+    ) -> io::Result<MptcpSocket<Self::Output>>
+    where
+        A: ToSocketAddrs + Send + Sync,
+    {
+        MptcpSocketBuilder::new()
+            .local_addr(local)
+            .connect(addr, opt)
+            .await
     }
 
-    #[tokio::test]
-    async fn test_mptcp_socket() {
-        let mptcp_enabled = is_mptcp_enabled();
+    async fn connect_mptcp_from_device<A>(
+        interface: &str,
+        addr: A,
+        opt: MptcpOpt,
+    ) -> io::Result<MptcpSocket<Self::Output>>
+    where
+        A: ToSocketAddrs + Send + Sync,
+    {
+        MptcpSocketBuilder::new()
+            .bind_device(interface)
+            .connect(addr, opt)
+            .await
+    }
 
-        let listener = TcpListener::bind_mptcp("127.0.0.1:0").await;
-        if mptcp_enabled {
-            assert!(matches!(listener, Ok(MptcpSocket::Mptcp(..))));
-        } else {
-            assert!(matches!(listener, Ok(MptcpSocket::Tcp(..))));
-        }
+    async fn connect_mptcp_with<A>(
+        addr: A,
+        options: ConnectOptions,
+    ) -> io::Result<MptcpSocket<Self::Output>>
+    where
+        A: ToSocketAddrs + Send + Sync,
+    {
+        MptcpSocketBuilder::from(options)
+            .connect(addr, MptcpOpt::Fallback)
+            .await
+    }
+}
+
+impl MptcpExt for TcpStream {}
+
+impl From<MptcpSocket<TcpStream>> for TcpStream {
+    fn from(socket: MptcpSocket<TcpStream>) -> Self {
+        socket.into_socket()
+    }
+}
+
+/// A `TcpStream` remembering whether it uses MPTCP.
+///
+/// Unlike `MptcpSocket<TcpStream>`, it implements `AsyncRead` and `AsyncWrite`, so it
+/// can be passed to generic code while keeping the MPTCP metadata. The status is
+/// checked when the stream is created: use `MptcpExt::mptcp_status` to detect a later
+/// fallback.
+#[derive(Debug)]
+pub struct MptcpStream {
+    inner: TcpStream,
+    status: MptcpStatus,
+}
+
+impl MptcpStream {
+    /// Returns the MPTCP status of the stream when it was created.
+    pub fn status(&self) -> MptcpStatus {
+        self.status
+    }
+
+    /// Returns whether the stream used MPTCP without fallback when it was created.
+    pub fn is_mptcp(&self) -> bool {
+        self.status.is_mptcp()
+    }
+
+    /// Returns information about the MPTCP connection, see `MptcpExt::mptcp_info`.
+    pub fn info(&self) -> io::Result<MptcpInfo> {
+        self.inner.mptcp_info()
+    }
+
+    /// Returns the addresses of the subflows, see `MptcpExt::mptcp_subflows`.
+    pub fn subflows(&self) -> io::Result<Vec<MptcpSubflow>> {
+        self.inner.mptcp_subflows()
+    }
+
+    /// Waits until at least `n` subflows are established, including the initial one.
+    ///
+    /// On Linux, path manager events are used when the process can subscribe to them
+    /// (this requires `CAP_NET_ADMIN` on recent kernels), otherwise `MPTCP_INFO` is
+    /// polled.
+    ///
+    /// # Arguments
+    ///
+    /// * `n` - The number of subflows to wait for.
+    /// * `timeout` - The maximum time to wait.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` containing the number of established subflows, or an
+    /// `io::Error` of kind `io::ErrorKind::TimedOut` if they were not established in
+    /// time. An error is also returned if the stream does not use MPTCP.
+    pub async fn wait_for_subflows(&self, n: usize, timeout: Duration) -> io::Result<usize> {
+        let deadline = Instant::now() + timeout;
+        let events = subscribe_pm_events();
+
+        loop {
+            let info = self.info()?;
+            let subflows = info.subflows as usize + 1;
+            if subflows >= n {
+                return Ok(subflows);
+            }
+
+            let changed = async {
+                match &events {
+                    Some(events) => {
+                        wait_pm_event(events, info.token).await;
+                    }
+                    None => sleep(POLL_INTERVAL).await,
+                }
+            };
+            if timeout_at(deadline, changed).await.is_err() {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "timed out waiting for subflows",
+                ));
+            }
+        }
+    }
+
+    /// Waits until the connection falls back to TCP.
+    ///
+    /// An established MPTCP connection can fall back to TCP, e.g. when a middlebox
+    /// corrupts the DSS checksum. The `MPTCP_INFO` flags are checked periodically and
+    /// when a path manager event is received for the connection (subscribing to them
+    /// requires `CAP_NET_ADMIN` on recent kernels). The returned future can be raced
+    /// against the I/O on the stream, or spawned with a callback.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `io::Result` that is `Ok` once the connection falls back, or
+    /// immediately if it already did. An `io::Error` of kind
+    /// `io::ErrorKind::NotConnected` is returned if the connection is closed without
+    /// falling back, and an error is also returned if the stream did not use MPTCP
+    /// or `MPTCP_INFO` is not supported.
+    pub async fn wait_for_fallback(&self) -> io::Result<()> {
+        let events = subscribe_pm_events();
+        let events = events.as_ref();
+
+        event::wait_for_fallback(&self.inner, self.status, |token| async move {
+            match events {
+                Some(events) => timeout(POLL_INTERVAL, wait_pm_event(events, token))
+                    .await
+                    .unwrap_or(PmUpdate::Unrelated),
+                None => {
+                    sleep(POLL_INTERVAL).await;
+                    PmUpdate::Unrelated
+                }
+            }
+        })
+        .await
+    }
+
+    /// Returns a reference to the underlying `TcpStream`.
+    pub fn get_ref(&self) -> &TcpStream {
+        &self.inner
+    }
+
+    /// Consumes the stream, returning the underlying `TcpStream`.
+    pub fn into_inner(self) -> TcpStream {
+        self.inner
+    }
+
+    /// Splits the stream into owned read and write halves, both keeping the MPTCP
+    /// status. See `TcpStream::into_split`.
+    pub fn into_split(self) -> (MptcpOwnedReadHalf, MptcpOwnedWriteHalf) {
+        let (read, write) = self.inner.into_split();
+        (
+            MptcpOwnedReadHalf {
+                inner: read,
+                status: self.status,
+            },
+            MptcpOwnedWriteHalf {
+                inner: write,
+                status: self.status,
+            },
+        )
+    }
+}
+
+/// Subscribes to the path manager events, registering the socket with the reactor.
+#[cfg(target_os = "linux")]
+fn subscribe_pm_events() -> Option<AsyncFd<sys::PmEvents>> {
+    let events = sys::PmEvents::subscribe().ok()?;
+    // SAFETY: `PmEvents` owns its socket, which stays open until it is dropped.
+    unsafe { AsyncFd::register(events).ok() }
+}
+
+/// Path manager events are only sent by Linux, `MPTCP_INFO` is polled elsewhere.
+#[cfg(not(target_os = "linux"))]
+fn subscribe_pm_events() -> Option<Infallible> {
+    None
+}
+
+/// Waits for a path manager event about the connection with the given token.
+///
+/// Returns how the events changed the connection, `PmUpdate::Unrelated` if they
+/// could not be received.
+#[cfg(target_os = "linux")]
+async fn wait_pm_event(events: &AsyncFd<sys::PmEvents>, token: u32) -> PmUpdate {
+    loop {
+        let mut guard = match events.readable().await {
+            Ok(guard) => guard,
+            Err(_) => {
+                sleep(POLL_INTERVAL).await;
+                return PmUpdate::Unrelated;
+            }
+        };
+        match guard.try_io(|events| events.get_ref().recv()) {
+            Ok(Ok(events)) => {
+                let update = PmUpdate::from_events(&events, token);
+                if update != PmUpdate::Unrelated {
+                    return update;
+                }
+            }
+            Err(_) => {}
+            // Events may have been lost, e.g. ENOBUFS
+            Ok(Err(_)) => {
+                sleep(POLL_INTERVAL).await;
+                return PmUpdate::Unrelated;
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+async fn wait_pm_event(events: &Infallible, _token: u32) -> PmUpdate {
+    match *events {}
+}
+
+impl From<TcpStream> for MptcpStream {
+    fn from(stream: TcpStream) -> Self {
+        let status = stream.mptcp_status();
+        Self {
+            inner: stream,
+            status,
+        }
+    }
+}
+
+impl From<MptcpSocket<TcpStream>> for MptcpStream {
+    fn from(socket: MptcpSocket<TcpStream>) -> Self {
+        socket.into_socket().into()
+    }
+}
+
+impl From<MptcpStream> for TcpStream {
+    fn from(stream: MptcpStream) -> Self {
+        stream.inner
+    }
+}
+
+impl AsyncRead for MptcpStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for MptcpStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+impl AsRawFd for MptcpStream {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+impl AsFd for MptcpStream {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.inner.as_fd()
+    }
+}
+
+impl MptcpExt for MptcpStream {}
+
+/// The MPTCP information of an accepted connection.
+///
+/// It is provided to axum handlers by `ConnectInfo` with the `axum` feature, and to
+/// tower services in the request extensions by `MptcpLayer` with the `tower` feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MptcpConnectionInfo {
+    /// The address of the client.
+    pub remote_addr: SocketAddr,
+    /// The MPTCP status of the connection when it was accepted.
+    pub status: MptcpStatus,
+}
+
+impl MptcpConnectionInfo {
+    /// Captures the information of an accepted connection.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream` - The accepted stream, e.g. from `MptcpListenerExt::accept_mptcp`.
+    /// * `remote_addr` - The address of the client.
+    ///
+    /// # Returns
+    ///
+    /// Returns the information about the connection.
+    pub fn new(stream: &MptcpStream, remote_addr: SocketAddr) -> Self {
+        Self {
+            remote_addr,
+            status: stream.status(),
+        }
+    }
+
+    /// Returns whether the client used MPTCP without fallback when the connection
+    /// was accepted.
+    pub fn is_mptcp(&self) -> bool {
+        self.status.is_mptcp()
+    }
+}
+
+/// The read half of an `MptcpStream`, see `MptcpStream::into_split`.
+#[derive(Debug)]
+pub struct MptcpOwnedReadHalf {
+    inner: OwnedReadHalf,
+    status: MptcpStatus,
+}
+
+impl MptcpOwnedReadHalf {
+    /// Returns the MPTCP status of the stream when it was created.
+    pub fn status(&self) -> MptcpStatus {
+        self.status
+    }
+
+    /// Returns whether the stream used MPTCP without fallback when it was created.
+    pub fn is_mptcp(&self) -> bool {
+        self.status.is_mptcp()
+    }
+
+    /// Returns information about the MPTCP connection, see `MptcpExt::mptcp_info`.
+    pub fn info(&self) -> io::Result<MptcpInfo> {
+        self.inner.as_ref().mptcp_info()
+    }
+
+    /// Returns the addresses of the subflows, see `MptcpExt::mptcp_subflows`.
+    pub fn subflows(&self) -> io::Result<Vec<MptcpSubflow>> {
+        self.inner.as_ref().mptcp_subflows()
+    }
+}
+
+impl AsyncRead for MptcpOwnedReadHalf {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+/// The write half of an `MptcpStream`, see `MptcpStream::into_split`.
+///
+/// Dropping the write half shuts down the write side of the stream.
+#[derive(Debug)]
+pub struct MptcpOwnedWriteHalf {
+    inner: OwnedWriteHalf,
+    status: MptcpStatus,
+}
+
+impl MptcpOwnedWriteHalf {
+    /// Returns the MPTCP status of the stream when it was created.
+    pub fn status(&self) -> MptcpStatus {
+        self.status
+    }
+
+    /// Returns whether the stream used MPTCP without fallback when it was created.
+    pub fn is_mptcp(&self) -> bool {
+        self.status.is_mptcp()
+    }
+
+    /// Returns information about the MPTCP connection, see `MptcpExt::mptcp_info`.
+    pub fn info(&self) -> io::Result<MptcpInfo> {
+        self.inner.as_ref().mptcp_info()
+    }
+
+    /// Returns the addresses of the subflows, see `MptcpExt::mptcp_subflows`.
+    pub fn subflows(&self) -> io::Result<Vec<MptcpSubflow>> {
+        self.inner.as_ref().mptcp_subflows()
+    }
+}
+
+impl AsyncWrite for MptcpOwnedWriteHalf {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+impl MptcpListenerExt for TcpListener {
+    type Output = Self;
+
+    async fn bind_mptcp_opt<A>(addr: A, opt: MptcpOpt) -> io::Result<MptcpSocket<Self::Output>>
+    where
+        A: ToSocketAddrs + Send + Sync,
+    {
+        MptcpSocketBuilder::new().bind(addr, opt).await
+    }
+
+    async fn bind_mptcp_with<A>(
+        addr: A,
+        options: ListenOptions,
+    ) -> io::Result<MptcpSocket<Self::Output>>
+    where
+        A: ToSocketAddrs + Send + Sync,
+    {
+        MptcpSocketBuilder::from(options)
+            .bind(addr, MptcpOpt::Fallback)
+            .await
+    }
+
+    async fn bind_mptcp_sharded<A>(
+        addr: A,
+        shards: usize,
+        opt: MptcpOpt,
+    ) -> io::Result<Vec<MptcpSocket<Self::Output>>>
+    where
+        A: ToSocketAddrs + Send + Sync,
+    {
+        MptcpSocketBuilder::new()
+            .bind_sharded(addr, shards, opt)
+            .await
+    }
+
+    async fn accept_mptcp(&self) -> io::Result<(MptcpSocket<TcpStream>, SocketAddr)> {
+        let (sock, addr) = self.accept().await?;
+        Ok((MptcpSocket::from_accepted(sock), addr))
+    }
+
+    async fn accept_mptcp_filtered(
+        &self,
+        filter: &AcceptFilter<TcpStream>,
+    ) -> io::Result<(TcpStream, SocketAddr)> {
+        filter.check_supported()?;
+        loop {
+            let (sock, addr) = self.accept().await?;
+            if let Some(sock) = filter.filter(sock, addr) {
+                return Ok((sock, addr));
+            }
+        }
+    }
+
+    fn incoming_mptcp(&self) -> IncomingMptcp<'_> {
+        IncomingMptcp { listener: self }
+    }
+}
+
+impl MptcpExt for TcpListener {}
+
+/// A stream of the connections of a `TcpListener`, see `incoming_mptcp`.
+#[derive(Debug)]
+pub struct IncomingMptcp<'a> {
+    listener: &'a TcpListener,
+}
+
+impl Stream for IncomingMptcp<'_> {
+    type Item = io::Result<MptcpSocket<TcpStream>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.listener
+            .poll_accept(cx)
+            .map(|res| Some(res.map(|(sock, _)| MptcpSocket::from_accepted(sock))))
+    }
+}
+
+impl MptcpExt for TcpSocket {}
+
+impl From<MptcpSocket<TcpListener>> for TcpListener {
+    fn from(socket: MptcpSocket<TcpListener>) -> Self {
+        socket.into_socket()
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    use std::{
+        net::{IpAddr, Ipv4Addr},
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use crate::{
+        sys::{has_mptcp_info, is_mptcp_enabled},
+        MptcpStatus,
+    };
+
+    fn assert_send<T: Send>(_: T) {}
+
+    // Only compiles if the futures are `Send` for any implementor of the traits,
+    // not only for the types of this module.
+    fn assert_generic_send<S, L, B>(addr: SocketAddr, listener: &L, builder: &B)
+    where
+        S: MptcpStreamExt,
+        L: MptcpListenerExt,
+        B: MptcpSocketBuilderExt,
+    {
+        assert_send(S::connect_mptcp(addr));
+        assert_send(S::connect_mptcp_force(addr));
+        assert_send(S::connect_mptcp_race(addr, Duration::from_millis(10)));
+        assert_send(S::connect_mptcp_with(addr, ConnectOptions::default()));
+        assert_send(L::bind_mptcp(addr));
+        assert_send(L::bind_mptcp_force(addr));
+        assert_send(L::bind_mptcp_with(addr, ListenOptions::default()));
+        assert_send(L::bind_mptcp_sharded(addr, 2, MptcpOpt::Fallback));
+        assert_send(listener.accept_mptcp());
+        assert_send(builder.connect(addr, MptcpOpt::Fallback));
+        assert_send(builder.bind(addr, MptcpOpt::Fallback));
+        assert_send(builder.bind_sharded(addr, 2, MptcpOpt::Fallback));
+    }
+
+    #[test]
+    fn test_futures_are_send() {
+        let _ = assert_generic_send::<TcpStream, TcpListener, MptcpSocketBuilder>;
+        let addr = "127.0.0.1:0";
+        let cache = FallbackCache::new(16, Duration::from_secs(60));
+        let builder = MptcpSocketBuilder::new();
+
+        assert_send(TcpStream::connect_mptcp(addr));
+        assert_send(TcpStream::connect_mptcp_opt(addr, MptcpOpt::NoFallback));
+        assert_send(TcpStream::connect_mptcp_force(addr));
+        assert_send(TcpStream::connect_mptcp_race(
+            addr,
+            Duration::from_millis(10),
+        ));
+        assert_send(TcpStream::connect_mptcp_cached(addr, &cache));
+        assert_send(TcpStream::connect_mptcp_from(
+            "127.0.0.1:0".parse().unwrap(),
+            addr,
+            MptcpOpt::Fallback,
+        ));
+        assert_send(TcpStream::connect_mptcp_from_device(
+            "lo",
+            addr,
+            MptcpOpt::Fallback,
+        ));
+        assert_send(TcpListener::bind_mptcp(addr));
+        assert_send(TcpListener::bind_mptcp_force(addr));
+        assert_send(TcpStream::connect_mptcp_with(
+            addr,
+            ConnectOptions::default(),
+        ));
+        assert_send(TcpListener::bind_mptcp_with(addr, ListenOptions::default()));
+        assert_send(builder.connect(addr, MptcpOpt::Fallback));
+        assert_send(builder.bind(addr, MptcpOpt::Fallback));
+        assert_send(TcpListener::bind_mptcp_sharded(addr, 2, MptcpOpt::Fallback));
+        assert_send(builder.bind_sharded(addr, 2, MptcpOpt::Fallback));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_spawn() {
+        let listener = TcpListener::bind_mptcp("127.0.0.1:0").await.unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        let stream = tokio::spawn(TcpStream::connect_mptcp(local_addr))
+            .await
+            .unwrap();
+        assert!(stream.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_each_addr() {
+        let addr = "127.0.0.1:80";
+        let result = resolve_each_addr(&addr, Attempt::mptcp("connect"), |addr| async move {
+            assert_eq!(addr.port(), 80);
+            assert_eq!(addr.ip(), IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)));
+            Ok(())
+        })
+        .await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_each_addr_error() {
+        let addr = "thisisanerror";
+        let result =
+            resolve_each_addr(&addr, Attempt::mptcp("connect"), |_| async { Ok(()) }).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mptcp_socket() {
+        let mptcp_enabled = is_mptcp_enabled();
+
+        let listener = TcpListener::bind_mptcp("127.0.0.1:0").await;
+        if mptcp_enabled {
+            assert!(matches!(listener, Ok(MptcpSocket::Mptcp(..))));
+        } else {
+            assert!(matches!(listener, Ok(MptcpSocket::Tcp(..))));
+        }
 
         let listener = listener.unwrap().into_socket();
         let local_addr = listener.local_addr().unwrap();
@@ -250,6 +1369,244 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_mptcp_listener_status() {
+        let mptcp_enabled = is_mptcp_enabled();
+
+        let listener = TcpListener::bind_mptcp("127.0.0.1:0").await.unwrap();
+        if mptcp_enabled {
+            assert!(matches!(
+                listener.mptcp_status(),
+                MptcpStatus::Mptcp {
+                    has_fallback: false
+                }
+            ));
+        } else {
+            assert!(matches!(listener.mptcp_status(), MptcpStatus::Tcp));
+        }
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        assert!(matches!(listener.mptcp_status(), MptcpStatus::Tcp));
+
+        let socket = TcpSocket::new_v4().unwrap();
+        assert!(matches!(socket.mptcp_status(), MptcpStatus::Tcp));
+    }
+
+    #[tokio::test]
+    async fn test_tcp_socket() {
+        let mptcp_enabled = is_mptcp_enabled();
+        let addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
+
+        let socket = TcpSocket::new_mptcp_for_addr(addr);
+        if !mptcp_enabled {
+            assert!(socket.is_err());
+            return;
+        }
+
+        let socket = socket.unwrap();
+        socket.set_reuseaddr(true).unwrap();
+        socket.bind(addr).unwrap();
+        let listener = socket.listen(1024).unwrap();
+        assert!(matches!(listener.mptcp_status(), MptcpStatus::Mptcp { .. }));
+        let local_addr = listener.local_addr().unwrap();
+
+        let socket = TcpSocket::new_mptcp_v4().unwrap();
+        socket.set_nodelay(true).unwrap();
+        let stream = socket.connect(local_addr).await.unwrap();
+        assert!(matches!(
+            stream.mptcp_status(),
+            MptcpStatus::Mptcp {
+                has_fallback: false
+            }
+        ));
+        assert!(TcpSocket::new_mptcp_v6().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_tcp_socket_opt() {
+        let mptcp_enabled = is_mptcp_enabled();
+        let addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
+
+        let socket = TcpSocket::new_mptcp_for_addr_opt(addr, MptcpOpt::Fallback).unwrap();
+        if mptcp_enabled {
+            assert!(matches!(socket, MptcpSocket::Mptcp(..)));
+        } else {
+            assert!(matches!(socket, MptcpSocket::Tcp(..)));
+        }
+
+        let socket = TcpSocket::new_mptcp_for_addr_opt(addr, MptcpOpt::NoFallback);
+        assert_eq!(socket.is_ok(), mptcp_enabled);
+    }
+
+    #[tokio::test]
+    async fn test_accept_mptcp_filtered() {
+        if !is_mptcp_enabled() || !has_mptcp_info() {
+            // Fallback can only be detected on >= 5.16 kernels
+            return;
+        }
+
+        let listener = TcpListener::bind_mptcp("127.0.0.1:0").await.unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        let rejected = Arc::new(AtomicUsize::new(0));
+        let counter = rejected.clone();
+        let filter = AcceptFilter::with_handler(move |_, _| {
+            counter.fetch_add(1, Ordering::Relaxed);
+        });
+
+        let _tcp = TcpStream::connect(local_addr).await.unwrap();
+        let mptcp = TcpStream::connect_mptcp(local_addr).await.unwrap();
+
+        let (accepted, addr) = listener.accept_mptcp_filtered(&filter).await.unwrap();
+        assert_eq!(addr, mptcp.local_addr().unwrap());
+        assert!(matches!(
+            accepted.mptcp_status(),
+            MptcpStatus::Mptcp {
+                has_fallback: false
+            }
+        ));
+        assert_eq!(filter.rejected(), 1);
+        assert_eq!(rejected.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn test_accept_mptcp() {
+        let mptcp_enabled = is_mptcp_enabled();
+
+        let listener = TcpListener::bind_mptcp("127.0.0.1:0").await.unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        let stream = TcpStream::connect_mptcp(local_addr).await.unwrap();
+        let (accepted, addr) = listener.accept_mptcp().await.unwrap();
+        assert_eq!(addr, stream.local_addr().unwrap());
+        if mptcp_enabled {
+            assert!(matches!(accepted, MptcpSocket::Mptcp(..)));
+        } else {
+            assert!(matches!(accepted, MptcpSocket::Tcp(..)));
+        }
+
+        let _stream = TcpStream::connect(local_addr).await.unwrap();
+        let (accepted, _) = listener.accept_mptcp().await.unwrap();
+        if !mptcp_enabled || has_mptcp_info() {
+            // Fallback can only be detected on >= 5.16 kernels
+            assert!(matches!(accepted, MptcpSocket::Tcp(..)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_incoming_mptcp() {
+        let mptcp_enabled = is_mptcp_enabled();
+
+        let listener = TcpListener::bind_mptcp("127.0.0.1:0").await.unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        let _stream = TcpStream::connect_mptcp(local_addr).await.unwrap();
+
+        let mut incoming = listener.incoming_mptcp();
+        let accepted = poll_fn(|cx| Pin::new(&mut incoming).poll_next(cx))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(matches!(accepted, MptcpSocket::Mptcp(..)), mptcp_enabled);
+    }
+
+    #[tokio::test]
+    async fn test_mptcp_stream() {
+        let mptcp_enabled = is_mptcp_enabled();
+
+        let listener = TcpListener::bind_mptcp("127.0.0.1:0").await.unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        let stream = MptcpStream::from(TcpStream::connect_mptcp(local_addr).await.unwrap());
+        let (accepted, _) = listener.accept().await.unwrap();
+        let mut accepted = MptcpStream::from(accepted);
+        assert_eq!(stream.is_mptcp(), mptcp_enabled);
+        assert_eq!(accepted.is_mptcp(), mptcp_enabled);
+        if mptcp_enabled && has_mptcp_info() {
+            assert!(stream.info().is_ok());
+            assert_eq!(stream.subflows().unwrap().len(), 1);
+        }
+
+        tokio::spawn(async move {
+            let mut buf = [0; 5];
+            accepted.read_exact(&mut buf).await.unwrap();
+            accepted.write_all(&buf).await.unwrap();
+        });
+
+        let status = stream.status();
+        let (mut read, mut write) = stream.into_split();
+        assert_eq!(read.status(), status);
+        assert_eq!(write.status(), status);
+        assert_eq!(write.is_mptcp(), mptcp_enabled);
+
+        write.write_all(b"hello").await.unwrap();
+        let mut buf = [0; 5];
+        read.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello");
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_subflows() {
+        if !is_mptcp_enabled() || !has_mptcp_info() {
+            return;
+        }
+
+        let listener = TcpListener::bind_mptcp("127.0.0.1:0").await.unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        let stream = MptcpStream::from(TcpStream::connect_mptcp(local_addr).await.unwrap());
+        let _accepted = listener.accept().await.unwrap();
+
+        assert_eq!(
+            stream
+                .wait_for_subflows(1, Duration::from_millis(100))
+                .await
+                .unwrap(),
+            1
+        );
+
+        // Additional subflows depend on the path manager configuration
+        let res = stream
+            .wait_for_subflows(8, Duration::from_millis(100))
+            .await;
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::TimedOut);
+
+        let stream = MptcpStream::from(TcpStream::connect(local_addr).await.unwrap());
+        assert!(stream
+            .wait_for_subflows(1, Duration::from_millis(100))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_fallback() {
+        if !is_mptcp_enabled() || !has_mptcp_info() {
+            return;
+        }
+
+        let listener = TcpListener::bind_mptcp("127.0.0.1:0").await.unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        let tcp = MptcpStream::from(TcpStream::connect(local_addr).await.unwrap());
+        let res = tcp.wait_for_fallback().await;
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+
+        // MPTCP clients fall back when the server does not use MPTCP
+        let tcp_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let tcp_addr = tcp_listener.local_addr().unwrap();
+        let fallback = MptcpStream::from(TcpStream::connect_mptcp(tcp_addr).await.unwrap());
+        let _accepted = tcp_listener.accept().await.unwrap();
+        fallback.wait_for_fallback().await.unwrap();
+
+        let stream = MptcpStream::from(TcpStream::connect_mptcp(local_addr).await.unwrap());
+        let _accepted = listener.accept().await.unwrap();
+        assert!(
+            tokio::time::timeout(Duration::from_millis(100), stream.wait_for_fallback())
+                .await
+                .is_err()
+        );
+    }
+
     #[tokio::test]
     async fn test_mptcp_no_fallback() {
         let mptcp_enabled = is_mptcp_enabled();
@@ -265,4 +1622,236 @@ mod tests {
         let stream = TcpStream::connect_mptcp_force("127.0.0.1:0").await;
         assert!(stream.is_err());
     }
+
+    #[tokio::test]
+    async fn test_mptcp_refused() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let local_addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        let stream = TcpStream::connect_mptcp_force(local_addr).await;
+        assert!(stream.is_err());
+
+        let stream = TcpStream::connect_mptcp(local_addr).await;
+        assert!(stream.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fast_open() {
+        let mptcp_enabled = is_mptcp_enabled();
+        let options = ListenOptions {
+            fast_open: true,
+            ..Default::default()
+        };
+
+        let listener = TcpListener::bind_mptcp_with("127.0.0.1:0", options)
+            .await
+            .unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        let options = ConnectOptions {
+            fast_open: true,
+            ..Default::default()
+        };
+        let mut stream = TcpStream::connect_mptcp_with(local_addr, options)
+            .await
+            .unwrap();
+        if mptcp_enabled {
+            assert!(matches!(stream, MptcpSocket::Mptcp(..)));
+        } else {
+            assert!(matches!(stream, MptcpSocket::Tcp(..)));
+        }
+        stream.write_all(b"hello").await.unwrap();
+
+        let (mut accepted, _) = listener.accept().await.unwrap();
+        let mut buf = [0; 5];
+        accepted.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello");
+    }
+
+    #[tokio::test]
+    async fn test_bind_mptcp_with() {
+        let mptcp_enabled = is_mptcp_enabled();
+        let options = ListenOptions {
+            backlog: 4096,
+            reuse_port: true,
+            ..Default::default()
+        };
+
+        let listener = TcpListener::bind_mptcp_with("127.0.0.1:0", options.clone()).await;
+        if mptcp_enabled {
+            assert!(matches!(listener, Ok(MptcpSocket::Mptcp(..))));
+        } else {
+            assert!(matches!(listener, Ok(MptcpSocket::Tcp(..))));
+        }
+
+        let local_addr = listener.unwrap().local_addr().unwrap();
+        let other = TcpListener::bind_mptcp_with(local_addr, options)
+            .await
+            .unwrap();
+        assert_eq!(other.local_addr().unwrap(), local_addr);
+    }
+
+    #[tokio::test]
+    async fn test_mptcp_sharded() {
+        let mptcp_enabled = is_mptcp_enabled();
+
+        let listeners = TcpListener::bind_mptcp_sharded("127.0.0.1:0", 4, MptcpOpt::Fallback)
+            .await
+            .unwrap();
+        assert_eq!(listeners.len(), 4);
+        if mptcp_enabled {
+            assert!(listeners
+                .iter()
+                .all(|listener| matches!(listener, MptcpSocket::Mptcp(..))));
+        } else {
+            assert!(listeners
+                .iter()
+                .all(|listener| matches!(listener, MptcpSocket::Tcp(..))));
+        }
+
+        let local_addr = listeners[0].local_addr().unwrap();
+        assert!(listeners
+            .iter()
+            .all(|listener| listener.local_addr().unwrap() == local_addr));
+
+        let mut streams = Vec::new();
+        for _ in 0..8 {
+            streams.push(TcpStream::connect_mptcp(local_addr).await.unwrap());
+        }
+
+        let mut accepted = 0;
+        for listener in &listeners {
+            while let Ok(res) = timeout(Duration::from_millis(50), listener.accept()).await {
+                res.unwrap();
+                accepted += 1;
+            }
+        }
+        assert_eq!(accepted, 8);
+    }
+
+    #[tokio::test]
+    async fn test_mptcp_from() {
+        let mptcp_enabled = is_mptcp_enabled();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        let stream = TcpStream::connect_mptcp_from(
+            "127.0.0.2:0".parse().unwrap(),
+            local_addr,
+            MptcpOpt::Fallback,
+        )
+        .await;
+        if mptcp_enabled {
+            assert!(matches!(stream, Ok(MptcpSocket::Mptcp(..))));
+        } else {
+            assert!(matches!(stream, Ok(MptcpSocket::Tcp(..))));
+        }
+        assert_eq!(
+            stream.unwrap().local_addr().unwrap().ip(),
+            IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mptcp_from_device() {
+        let mptcp_enabled = is_mptcp_enabled();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        let stream =
+            TcpStream::connect_mptcp_from_device("lo", local_addr, MptcpOpt::Fallback).await;
+        if mptcp_enabled {
+            assert!(matches!(stream, Ok(MptcpSocket::Mptcp(..))));
+        } else {
+            assert!(matches!(stream, Ok(MptcpSocket::Tcp(..))));
+        }
+
+        let stream =
+            TcpStream::connect_mptcp_from_device("nonexistent0", local_addr, MptcpOpt::Fallback)
+                .await;
+        assert!(stream.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mptcp_cached() {
+        let mptcp_enabled = is_mptcp_enabled();
+        let cache = FallbackCache::new(16, Duration::from_secs(60));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        let stream = TcpStream::connect_mptcp_cached(local_addr, &cache)
+            .await
+            .unwrap();
+        if !mptcp_enabled || !has_mptcp_info() {
+            // Fallback can only be detected on >= 5.16 kernels
+            return;
+        }
+        assert!(matches!(stream, MptcpSocket::Mptcp(..)));
+        assert!(cache.contains(&local_addr));
+
+        let stream = TcpStream::connect_mptcp_cached(local_addr, &cache)
+            .await
+            .unwrap();
+        assert!(matches!(stream, MptcpSocket::Tcp(..)));
+    }
+
+    #[tokio::test]
+    async fn test_mptcp_race() {
+        let mptcp_enabled = is_mptcp_enabled();
+
+        let listener = TcpListener::bind_mptcp("127.0.0.1:0")
+            .await
+            .unwrap()
+            .into_socket();
+        let local_addr = listener.local_addr().unwrap();
+
+        let stream = TcpStream::connect_mptcp_race(local_addr, Duration::from_secs(1)).await;
+        if mptcp_enabled {
+            assert!(matches!(stream, Ok(MptcpSocket::Mptcp(..))));
+        } else {
+            assert!(matches!(stream, Ok(MptcpSocket::Tcp(..))));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mptcp_race_refused() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let local_addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        let stream = TcpStream::connect_mptcp_race(local_addr, Duration::from_secs(1)).await;
+        assert!(stream.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_watch_stats() {
+        let listener = TcpListener::bind_mptcp("127.0.0.1:0").await.unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        let mut stream = TcpStream::connect_mptcp(local_addr).await.unwrap();
+        let (mut accepted, _) = listener.accept().await.unwrap();
+
+        let stats = stream.watch_stats(Duration::from_millis(10));
+        if !is_mptcp_enabled() || !has_mptcp_info() {
+            assert!(stats.is_err());
+            return;
+        }
+        let mut stats = stats.unwrap();
+        assert!(stream.watch_stats(Duration::ZERO).is_err());
+        assert_eq!(stats.borrow().elapsed, Duration::ZERO);
+
+        stream.write_all(&[0; 1000]).await.unwrap();
+        let mut buf = [0; 1000];
+        accepted.read_exact(&mut buf).await.unwrap();
+
+        stats.changed().await.unwrap();
+        assert!(stats.borrow_and_update().elapsed > Duration::ZERO);
+
+        drop(stream);
+        while stats.changed().await.is_ok() {}
+    }
 }
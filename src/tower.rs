@@ -0,0 +1,189 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use http::{HeaderName, HeaderValue, Request, Response};
+use pin_project_lite::pin_project;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::tokio::MptcpConnectionInfo;
+
+/// The name of the response header added by `MptcpLayer::response_header`.
+pub const X_MPTCP: HeaderName = HeaderName::from_static("x-mptcp");
+
+/// A layer inserting the `MptcpConnectionInfo` of a connection in the extensions of
+/// its requests, where services can get it with e.g. axum's `Extension` extractor.
+///
+/// A layer is created for each accepted connection, and applied to the service
+/// handling the requests of this connection.
+#[derive(Debug, Clone, Copy)]
+pub struct MptcpLayer {
+    info: MptcpConnectionInfo,
+    response_header: bool,
+}
+
+impl MptcpLayer {
+    /// Creates a new layer for the connection described by `info`.
+    pub fn new(info: MptcpConnectionInfo) -> Self {
+        Self {
+            info,
+            response_header: false,
+        }
+    }
+
+    /// Sets whether to add an `X-MPTCP` header to the responses, with value `true`
+    /// if the client uses MPTCP, `false` otherwise. Disabled by default.
+    pub fn response_header(mut self, enable: bool) -> Self {
+        self.response_header = enable;
+        self
+    }
+}
+
+impl<S> Layer<S> for MptcpLayer {
+    type Service = MptcpService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MptcpService {
+            inner,
+            info: self.info,
+            response_header: self.response_header,
+        }
+    }
+}
+
+/// The service created by `MptcpLayer`.
+#[derive(Debug, Clone)]
+pub struct MptcpService<S> {
+    inner: S,
+    info: MptcpConnectionInfo,
+    response_header: bool,
+}
+
+impl<S> MptcpService<S> {
+    /// Returns the information inserted in the requests.
+    pub fn info(&self) -> &MptcpConnectionInfo {
+        &self.info
+    }
+
+    /// Returns a reference to the inner service.
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// Consumes the service, returning the inner service.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for MptcpService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        req.extensions_mut().insert(self.info);
+        let header = self.response_header.then(|| {
+            HeaderValue::from_static(if self.info.is_mptcp() {
+                "true"
+            } else {
+                "false"
+            })
+        });
+
+        ResponseFuture {
+            inner: self.inner.call(req),
+            header,
+        }
+    }
+}
+
+pin_project! {
+    /// The response future of `MptcpService`.
+    pub struct ResponseFuture<F> {
+        #[pin]
+        inner: F,
+        header: Option<HeaderValue>,
+    }
+}
+
+impl<F, B, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response<B>, E>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut res = ready!(this.inner.poll(cx))?;
+        if let Some(header) = this.header.take() {
+            res.headers_mut().insert(X_MPTCP, header);
+        }
+        Poll::Ready(Ok(res))
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    use std::{convert::Infallible, future::Ready};
+
+    use tokio::net::{TcpListener, TcpStream};
+
+    use crate::{
+        sys::is_mptcp_enabled,
+        tokio::{MptcpListenerExt, MptcpStream, MptcpStreamExt},
+    };
+
+    #[derive(Clone)]
+    struct Echo;
+
+    impl Service<Request<()>> for Echo {
+        type Response = Response<Option<MptcpConnectionInfo>>;
+        type Error = Infallible;
+        type Future = Ready<Result<Self::Response, Self::Error>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: Request<()>) -> Self::Future {
+            let info = req.extensions().get::<MptcpConnectionInfo>().copied();
+            std::future::ready(Ok(Response::new(info)))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mptcp_layer() {
+        let mptcp_enabled = is_mptcp_enabled();
+
+        let listener = TcpListener::bind_mptcp("127.0.0.1:0").await.unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        let _stream = TcpStream::connect_mptcp(local_addr).await.unwrap();
+        let (accepted, remote_addr) = listener.accept_mptcp().await.unwrap();
+        let info = MptcpConnectionInfo::new(&MptcpStream::from(accepted), remote_addr);
+        assert_eq!(info.is_mptcp(), mptcp_enabled);
+
+        let mut service = MptcpLayer::new(info).layer(Echo);
+        let res = service.call(Request::new(())).await.unwrap();
+        assert_eq!(res.body(), &Some(info));
+        assert!(res.headers().get(X_MPTCP).is_none());
+
+        let mut service = MptcpLayer::new(info).response_header(true).layer(Echo);
+        let res = service.call(Request::new(())).await.unwrap();
+        let expected = if mptcp_enabled { "true" } else { "false" };
+        assert_eq!(res.headers()[X_MPTCP], expected);
+    }
+}